@@ -1,5 +1,9 @@
 use criterion::{black_box, Criterion, criterion_group, criterion_main};
 
+use urubu::advanced::attack_info::AttackInfo;
+use urubu::advanced::board::Board;
+use urubu::advanced::move_list::MoveList;
+use urubu::advanced::perft::Perft;
 use urubu::types::color::Color;
 use urubu::types::square::Square;
 
@@ -21,5 +25,43 @@ fn square_forward(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, color_to_char, color_invert, square_forward);
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -";
+
+fn perft_copy_make(board: &Board, move_list: &mut MoveList, attack_info: &mut AttackInfo, depth: u8) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    move_list.start_ply();
+    move_list.generate_quiets(board, attack_info);
+    move_list.generate_noisy(board, attack_info);
+    let mut result = 0;
+    while move_list.has_next() {
+        let board_move = move_list.next();
+        let mut clone = board.clone();
+        if clone.do_move(&board_move) {
+            result += perft_copy_make(&clone, move_list, attack_info, depth - 1);
+        }
+    }
+    move_list.end_ply();
+    result
+}
+
+fn perft_copy(c: &mut Criterion) {
+    let board = Board::from_fen(KIWIPETE);
+    let mut move_list = MoveList::new();
+    let mut attack_info = AttackInfo::new();
+    c.bench_function("perft_copy", |b| {
+        b.iter(|| perft_copy_make(black_box(&board), &mut move_list, &mut attack_info, 3));
+    });
+}
+
+fn perft_undo(c: &mut Criterion) {
+    let mut board = Board::from_fen(KIWIPETE);
+    let mut perft = Perft::new();
+    c.bench_function("perft_undo", |b| {
+        b.iter(|| perft.perft(black_box(&mut board), 3));
+    });
+}
+
+criterion_group!(benches, color_to_char, color_invert, square_forward, perft_copy, perft_undo);
 criterion_main!(benches);
\ No newline at end of file
@@ -65,6 +65,12 @@ impl Square {
         NEIGHBOUR[self.to_usize()]
     }
 
+    // No enemy pawn on these squares means the pawn is passed
+    #[inline]
+    pub fn passed_pawn_mask(&self, color: &Color) -> Bitboard {
+        PASSED_PAWN_MASK[color.to_usize()][self.to_usize()]
+    }
+
     #[inline]
     pub fn pinned_mask(&self, other: &Square) -> Bitboard {
         PINNED_MASK[self.to_usize()][other.to_usize()]
@@ -168,6 +174,15 @@ mod test {
         assert_eq!(Square::D3.neighbour(), Bitboard::C3.union(&Bitboard::E3));
     }
 
+    #[test]
+    fn passed_pawn_mask() {
+        assert_eq!(Square::A6.passed_pawn_mask(&Color::White), Bitboard::A7.union(&Bitboard::B7)
+            .union(&Bitboard::A8).union(&Bitboard::B8));
+        assert_eq!(Square::E3.passed_pawn_mask(&Color::Black), Bitboard::D2.union(&Bitboard::E2)
+            .union(&Bitboard::F2).union(&Bitboard::D1).union(&Bitboard::E1).union(&Bitboard::F1));
+        assert_eq!(Square::H8.passed_pawn_mask(&Color::White), Bitboard::EMPTY);
+    }
+
     #[test]
     fn bishop_move() {
         assert_eq!(Square::A1.pseudo_bishop_moves(), Bitboard::B2.union(&Bitboard::C3)
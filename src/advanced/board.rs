@@ -1,5 +1,6 @@
 use std::fmt::{Debug, Error, Formatter};
 
+use crate::advanced::board_state::BoardState;
 use crate::advanced::zobrist_key::ZobristKey;
 use crate::types::bitboard::Bitboard;
 use crate::types::board_move::BoardMove;
@@ -32,6 +33,8 @@ pub struct Board {
     pub pinned_bitboard: Bitboard,
     pub danger_bitboard: [[Bitboard; PieceType::NUM_PIECE_TYPES]; Color::NUM_COLORS],
     pub check_bitboard: Bitboard,
+
+    history: Vec<BoardState>,
 }
 
 impl Board {
@@ -66,6 +69,8 @@ impl Board {
             check_bitboard: Bitboard::EMPTY,
             pinned_bitboard: Bitboard::EMPTY,
             danger_bitboard: [[Bitboard::EMPTY; PieceType::NUM_PIECE_TYPES]; Color::NUM_COLORS],
+
+            history: Vec::new(),
         };
 
         result.castling_rights_masks[result.king_square(&Color::White).to_usize()] = CastlingRights::WHITE_RIGHTS;
@@ -112,15 +117,17 @@ impl Board {
             check_bitboard: Bitboard::EMPTY,
             pinned_bitboard: Bitboard::EMPTY,
             danger_bitboard: [[Bitboard::EMPTY; PieceType::NUM_PIECE_TYPES]; Color::NUM_COLORS],
+
+            history: Vec::new(),
         };
 
         result.castling_rights_masks[result.king_square(&Color::White).to_usize()] = CastlingRights::WHITE_RIGHTS;
         result.castling_rights_masks[result.king_square(&Color::Black).to_usize()] = CastlingRights::BLACK_RIGHTS;
 
-        result.castling_rights_masks[result.initial_rook_square[CastlingIndex::WhiteA.to_usize()].to_usize()] = CastlingRights::WHITE_OO;
-        result.castling_rights_masks[result.initial_rook_square[CastlingIndex::WhiteH.to_usize()].to_usize()] = CastlingRights::WHITE_OOO;
-        result.castling_rights_masks[result.initial_rook_square[CastlingIndex::BlackA.to_usize()].to_usize()] = CastlingRights::BLACK_OO;
-        result.castling_rights_masks[result.initial_rook_square[CastlingIndex::BlackH.to_usize()].to_usize()] = CastlingRights::BLACK_OOO;
+        result.castling_rights_masks[result.initial_rook_square[CastlingIndex::WhiteA.to_usize()].to_usize()] = CastlingRights::WHITE_OOO;
+        result.castling_rights_masks[result.initial_rook_square[CastlingIndex::WhiteH.to_usize()].to_usize()] = CastlingRights::WHITE_OO;
+        result.castling_rights_masks[result.initial_rook_square[CastlingIndex::BlackA.to_usize()].to_usize()] = CastlingRights::BLACK_OOO;
+        result.castling_rights_masks[result.initial_rook_square[CastlingIndex::BlackH.to_usize()].to_usize()] = CastlingRights::BLACK_OO;
 
         result.compute_zobrist();
         result.initial_pass();
@@ -201,6 +208,11 @@ impl Board {
         self.piece_bitboard[piece_type.to_usize()].intersect(&self.color_bitboard(color))
     }
 
+    #[inline]
+    pub fn piece_type_bitboard(&self, piece_type: &PieceType) -> Bitboard {
+        self.piece_bitboard[piece_type.to_usize()]
+    }
+
     #[inline]
     pub fn color_bitboard(&self, color: &Color) -> Bitboard {
         self.color_bitboard[color.to_usize()]
@@ -273,19 +285,56 @@ impl Board {
         self.zkey_pawn = pawn_zobrist_key;
     }
 
+    // Rights are lost once the king or the rook leaves its initial square or the rook is captured
+    pub fn set_castling_rooks(&mut self, castling_rooks: &[(CastlingIndex, Square)]) {
+        self.castling_rights_masks = [CastlingRights::NO_CASTLING; Square::NUM_SQUARES];
+        for (castling_index, rook_square) in castling_rooks.iter() {
+            let right = CastlingRights::NO_CASTLING.add_index(castling_index);
+            let king_square = self.king_square(&castling_index.color());
+            self.initial_rook_square[castling_index.to_usize()] = *rook_square;
+            self.castling_rights_masks[king_square.to_usize()] = self.castling_rights_masks[king_square.to_usize()].union(&right);
+            self.castling_rights_masks[rook_square.to_usize()] = self.castling_rights_masks[rook_square.to_usize()].union(&right);
+        }
+    }
+
+    // Castling moves go from the king to its rook, the side is the one of the rook
     #[inline]
-    fn do_castle(&mut self, color: &Color, square_from: &Square, square_to: &Square) {
+    pub fn castling_index(color: &Color, square_from: &Square, square_to: &Square) -> CastlingIndex {
         let castling_side = if square_to.0 > square_from.0 {
             CastlingSide::HSide
         } else {
             CastlingSide::ASide
         };
-        let castling_index = CastlingIndex::from_color_side(color, &castling_side);
-        let square_rook_from = self.initial_rook_square[castling_index.to_usize()];
+        CastlingIndex::from_color_side(color, &castling_side)
+    }
+
+    // King and rook are lifted before being placed, in Chess960 their squares may overlap
+    #[inline]
+    fn do_castle(&mut self, color: &Color, square_from: &Square, square_to: &Square) {
+        let castling_index = Board::castling_index(color, square_from, square_to);
+        let square_king_to = castling_index.square_king_to();
+        let square_rook_to = castling_index.square_rook_to();
+
+        self.remove_piece(color, &PieceType::KING, square_from);
+        self.remove_piece(color, &PieceType::ROOK, square_to);
+        self.add_piece(color, &PieceType::KING, &square_king_to);
+        self.add_piece(color, &PieceType::ROOK, &square_rook_to);
+        self.zkey.move_piece(color, &PieceType::KING, square_from, &square_king_to);
+        self.zkey.move_piece(color, &PieceType::ROOK, square_to, &square_rook_to);
+        self.king_square[color.to_usize()] = square_king_to;
+    }
+
+    #[inline]
+    fn undo_castle(&mut self, color: &Color, square_from: &Square, square_to: &Square) {
+        let castling_index = Board::castling_index(color, square_from, square_to);
+        let square_king_to = castling_index.square_king_to();
         let square_rook_to = castling_index.square_rook_to();
 
-        self.move_piece(color, &PieceType::KING, &square_from, &square_to);
-        self.move_piece(color, &PieceType::ROOK, &square_rook_from, &square_rook_to);
+        self.remove_piece(color, &PieceType::KING, &square_king_to);
+        self.remove_piece(color, &PieceType::ROOK, &square_rook_to);
+        self.add_piece(color, &PieceType::KING, square_from);
+        self.add_piece(color, &PieceType::ROOK, square_to);
+        self.king_square[color.to_usize()] = *square_from;
     }
 
     #[inline]
@@ -296,26 +345,42 @@ impl Board {
         }
     }
 
+    // Returns false when the move leaves our king in check, it still has to be undone
     pub fn do_move(&mut self, board_move: &BoardMove) -> bool {
-        self.rule_50 += 1;
-
         let square_from = board_move.square_from();
         let square_to = board_move.square_to();
         let piece_type = self.piece_type(&square_from);
-        let mut piece_captured = self.piece_type(&square_to);
         let move_type = board_move.move_type();
+        // Castling moves target our own rook
+        let mut piece_captured = if move_type.is_castling() {
+            PieceType::NONE
+        } else {
+            self.piece_type(&square_to)
+        };
+
+        self.history.push(BoardState {
+            piece_captured: if move_type.is_passant() { PieceType::PAWN } else { piece_captured },
+            castling_rights: self.castling_rights,
+            ep_square: self.ep_square,
+            rule_50: self.rule_50,
+            zkey: self.zkey,
+            zkey_pawn: self.zkey_pawn,
+            pinned_bitboard: self.pinned_bitboard,
+            check_bitboard: self.check_bitboard,
+            danger_bitboard: self.danger_bitboard,
+        });
+        self.rule_50 += 1;
 
         let color_our = self.color_to_move;
         let color_their = color_our.reverse();
 
         self.zkey.set_color();
-        self.zkey.move_piece(&color_our, &piece_type, &square_from, &square_to);
 
         // Castling needs to move two pieces
         if move_type.is_castling() {
             self.do_castle(&color_our, &square_from, &square_to);
-            self.zkey.move_piece(&color_our, &PieceType::ROOK, &square_from, &square_to);
         } else {
+            self.zkey.move_piece(&color_our, &piece_type, &square_from, &square_to);
             let mut square_captured = square_to;
             if move_type.is_passant() {
                 square_captured = square_captured.forward(&color_their);
@@ -353,12 +418,13 @@ impl Board {
                 self.zkey_pawn.change_piece(&color_our, &piece_type, &square_to);
             }
             self.rule_50 = 0;
-        } else if piece_type == PieceType::KING {
+        } else if piece_type == PieceType::KING && !move_type.is_castling() {
             self.king_square[color_our.to_usize()] = square_to;
         }
 
         self.update_castling_rights(&square_from, &square_to);
         self.color_to_move = color_their;
+        self.move_number += 1;
 
         self.first_pass();
         if self.check_bitboard.is_not_empty() {
@@ -368,6 +434,107 @@ impl Board {
         return true;
     }
 
+    // Takes back the last move done, legal or not
+    pub fn undo_move(&mut self, board_move: &BoardMove) {
+        let state = self.history.pop().expect("no move to undo");
+
+        let square_from = board_move.square_from();
+        let square_to = board_move.square_to();
+        let move_type = board_move.move_type();
+
+        let color_our = self.color_to_move.reverse();
+        let color_their = self.color_to_move;
+        self.color_to_move = color_our;
+        self.move_number -= 1;
+
+        if move_type.is_castling() {
+            self.undo_castle(&color_our, &square_from, &square_to);
+        } else {
+            let promoted_piece = move_type.promoted_piece_type();
+            let piece_type = if promoted_piece != PieceType::NONE {
+                self.remove_piece(&color_our, &promoted_piece, &square_to);
+                self.add_piece(&color_our, &PieceType::PAWN, &square_to);
+                PieceType::PAWN
+            } else {
+                self.piece_type(&square_to)
+            };
+            self.move_piece(&color_our, &piece_type, &square_to, &square_from);
+            if piece_type == PieceType::KING {
+                self.king_square[color_our.to_usize()] = square_from;
+            }
+
+            if state.piece_captured != PieceType::NONE {
+                let square_captured = if move_type.is_passant() {
+                    square_to.forward(&color_their)
+                } else {
+                    square_to
+                };
+                self.add_piece(&color_their, &state.piece_captured, &square_captured);
+            }
+        }
+
+        self.castling_rights = state.castling_rights;
+        self.ep_square = state.ep_square;
+        self.rule_50 = state.rule_50;
+        self.zkey = state.zkey;
+        self.zkey_pawn = state.zkey_pawn;
+        self.pinned_bitboard = state.pinned_bitboard;
+        self.check_bitboard = state.check_bitboard;
+        self.danger_bitboard = state.danger_bitboard;
+
+        self.piece_bitboard[PieceType::NONE.to_usize()] = self.color_bitboard[Color::White.to_usize()]
+            .union(&self.color_bitboard[Color::Black.to_usize()]);
+    }
+
+    // Passes the turn to the other side, which is only sound when not in check
+    pub fn do_null_move(&mut self) {
+        self.history.push(BoardState {
+            piece_captured: PieceType::NONE,
+            castling_rights: self.castling_rights,
+            ep_square: self.ep_square,
+            rule_50: self.rule_50,
+            zkey: self.zkey,
+            zkey_pawn: self.zkey_pawn,
+            pinned_bitboard: self.pinned_bitboard,
+            check_bitboard: self.check_bitboard,
+            danger_bitboard: self.danger_bitboard,
+        });
+        self.rule_50 += 1;
+        self.zkey.set_color();
+        self.clear_ep();
+        self.color_to_move = self.color_to_move.reverse();
+        self.move_number += 1;
+        self.second_pass();
+    }
+
+    pub fn undo_null_move(&mut self) {
+        let state = self.history.pop().expect("no move to undo");
+        self.color_to_move = self.color_to_move.reverse();
+        self.move_number -= 1;
+        self.castling_rights = state.castling_rights;
+        self.ep_square = state.ep_square;
+        self.rule_50 = state.rule_50;
+        self.zkey = state.zkey;
+        self.zkey_pawn = state.zkey_pawn;
+        self.pinned_bitboard = state.pinned_bitboard;
+        self.check_bitboard = state.check_bitboard;
+        self.danger_bitboard = state.danger_bitboard;
+    }
+
+    // Copy of the current position without the moves done to reach it, so it can not undo past it
+    pub fn clone_position(&self) -> Self {
+        Self {
+            history: Vec::new(),
+            ..*self
+        }
+    }
+
+    // State before each move done, oldest first
+    #[inline]
+    pub fn history(&self) -> &[BoardState] {
+        &self.history
+    }
+
     #[inline]
     pub fn initial_pass(&mut self) {
         self.first_pass();
@@ -486,7 +653,9 @@ impl Debug for Board {
 
 #[cfg(test)]
 mod test {
+    use crate::advanced::attack_info::AttackInfo;
     use crate::advanced::board::Board;
+    use crate::advanced::move_list::MoveList;
     use crate::types::bitboard::Bitboard;
     use crate::types::color::Color;
     use crate::types::piece_type::PieceType;
@@ -517,11 +686,135 @@ mod test {
         assert_eq!(board.piece_type(&Square::A8), PieceType::ROOK);
     }
 
+    #[test]
+    fn default_board_matches_start_fen() {
+        let board = Board::default();
+        let from_fen = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_same_state(&board, &from_fen);
+        assert_eq!(board.castling_rights_masks, from_fen.castling_rights_masks);
+    }
+
+    #[test]
+    fn rook_move_removes_own_side_castling() {
+        let mut board = Board::default();
+        board.do_move(&BoardMove::build_normal(&Square::A2, &Square::A4));
+        board.do_move(&BoardMove::build_normal(&Square::H7, &Square::H5));
+        board.do_move(&BoardMove::build_normal(&Square::A1, &Square::A3));
+        board.do_move(&BoardMove::build_normal(&Square::H8, &Square::H6));
+        assert_eq!(board.castling_rights, CastlingRights::WHITE_OO.union(&CastlingRights::BLACK_OOO));
+    }
+
+    #[test]
+    fn castling_updates_zobrist_key() {
+        let mut board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq -");
+        board.do_move(&BoardMove::build_castling(&Square::E1, &Square::H1));
+        board.do_move(&BoardMove::build_castling(&Square::E8, &Square::A8));
+        let incremental_key = board.zkey;
+        board.compute_zobrist();
+        assert_eq!(incremental_key, board.zkey);
+    }
+
+    #[test]
+    fn null_move() {
+        let mut board = Board::from_fen("4k3/8/8/3pP3/8/8/8/R3K2b w Q d6 0 1");
+        let original = board.clone();
+        board.do_null_move();
+        assert_eq!(board.color_to_move, Color::Black);
+        assert_eq!(board.ep_square, None);
+        assert!(board.check_bitboard.is_empty());
+        let incremental_key = board.zkey;
+        board.compute_zobrist();
+        assert_eq!(incremental_key, board.zkey);
+        let mut move_list = MoveList::new();
+        let mut attack_info = AttackInfo::new();
+        let moves = board.legal_moves(&mut move_list, &mut attack_info).len();
+        let expected = Board::from_fen("4k3/8/8/3pP3/8/8/8/R3K2b b Q - 0 1").legal_moves(&mut move_list, &mut attack_info).len();
+        assert_eq!(moves, expected);
+        board.undo_null_move();
+        assert_same_state(&board, &original);
+    }
+
+    fn assert_same_state(board: &Board, other: &Board) {
+        assert_eq!(board, other);
+        assert_eq!(board.ep_square, other.ep_square);
+        assert_eq!(board.zkey, other.zkey);
+        assert_eq!(board.zkey_pawn, other.zkey_pawn);
+        assert_eq!(board.king_square, other.king_square);
+        assert_eq!(board.pinned_bitboard, other.pinned_bitboard);
+        assert_eq!(board.danger_bitboard, other.danger_bitboard);
+        assert_eq!(board.check_bitboard, other.check_bitboard);
+        assert_eq!(board.game_bitboard(), other.game_bitboard());
+    }
+
+    fn check_undo(board: &mut Board, move_list: &mut MoveList, attack_info: &mut AttackInfo, depth: u8) {
+        if depth == 0 {
+            return;
+        }
+        let original = board.clone();
+        move_list.start_ply();
+        move_list.generate_noisy(board, attack_info);
+        move_list.generate_quiets(board, attack_info);
+        while move_list.has_next() {
+            let board_move = move_list.next();
+            if board.do_move(&board_move) {
+                check_undo(board, move_list, attack_info, depth - 1);
+            }
+            board.undo_move(&board_move);
+            assert_same_state(board, &original);
+        }
+        move_list.end_ply();
+    }
+
+    #[test]
+    fn undo_move_restores_board() {
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ -",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - -",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        ];
+        let mut move_list = MoveList::new();
+        let mut attack_info = AttackInfo::new();
+        for fen in fens.iter() {
+            let mut board = Board::from_fen(fen);
+            check_undo(&mut board, &mut move_list, &mut attack_info, 3);
+            assert!(board.history().is_empty());
+        }
+    }
+
+    #[test]
+    fn undo_en_passant() {
+        let mut board = Board::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6");
+        let original = board.clone();
+        let board_move = BoardMove::build_passant(&Square::E5, &Square::D6);
+        assert!(board.do_move(&board_move));
+        assert_eq!(board.piece_type(&Square::D5), PieceType::NONE);
+        assert_eq!(board.history()[0].piece_captured, PieceType::PAWN);
+        board.undo_move(&board_move);
+        assert_same_state(&board, &original);
+        assert_eq!(board.piece_type(&Square::D5), PieceType::PAWN);
+    }
+
+    #[test]
+    fn clone_position() {
+        let mut board = Board::default();
+        let board_move = BoardMove::build_normal(&Square::E2, &Square::E4);
+        board.do_move(&board_move);
+        let mut position = board.clone_position();
+        assert!(position.history().is_empty());
+        assert_same_state(&position, &board);
+        let reply = BoardMove::build_normal(&Square::E7, &Square::E5);
+        let original = position.clone_position();
+        position.do_move(&reply);
+        position.undo_move(&reply);
+        assert_same_state(&position, &original);
+    }
+
     #[test]
     fn pinned() {
         let mut board = Board::default();
         board.set_check_bitboard(&Color::White);
-        assert_eq!(board.check_bitboard.is_empty(), true);
+        assert!(board.check_bitboard.is_empty());
     }
 
     #[test]
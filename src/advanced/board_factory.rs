@@ -4,61 +4,248 @@ use crate::types::file::File;
 use crate::types::rank::Rank;
 use crate::types::piece_type::PieceType;
 use crate::types::color::Color;
-use crate::types::castling_rights::CastlingRights;
-use std::cmp;
-use std::cmp::max;
+use crate::types::castling_rights::{CastlingIndex, CastlingRights, CastlingSide};
+use std::error::Error;
+use std::fmt;
 use crate::types::bitboard::Bitboard;
 
+#[derive(PartialEq, Clone, Debug)]
+pub enum FenError {
+    MissingField(&'static str),
+    InvalidPiece(char),
+    RankOverflow(Rank),
+    RankUnderflow(Rank),
+    InvalidRankCount(usize),
+    MissingKing(Color),
+    InvalidSideToMove(String),
+    InvalidCastling(String),
+    InvalidEnPassant(String),
+    InvalidClock(String),
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FenError::MissingField(field) => write!(formatter, "missing {}", field),
+            FenError::InvalidPiece(c) => write!(formatter, "invalid piece '{}'", c),
+            FenError::RankOverflow(rank) => write!(formatter, "rank {} has more than 8 squares", rank.to_char()),
+            FenError::RankUnderflow(rank) => write!(formatter, "rank {} has fewer than 8 squares", rank.to_char()),
+            FenError::InvalidRankCount(count) => write!(formatter, "expected 8 ranks, found {}", count),
+            FenError::MissingKing(color) => write!(formatter, "missing king for {}", color.to_char()),
+            FenError::InvalidSideToMove(token) => write!(formatter, "invalid side to move '{}'", token),
+            FenError::InvalidCastling(token) => write!(formatter, "invalid castling rights '{}'", token),
+            FenError::InvalidEnPassant(token) => write!(formatter, "invalid en passant square '{}'", token),
+            FenError::InvalidClock(token) => write!(formatter, "invalid clock '{}'", token),
+        }
+    }
+}
+
+impl Error for FenError {}
+
 impl Board {
     const EMPTY_SPACE: char = ' ';
     const SEPARATOR: char = '/';
 
+    pub const CHESS960_POSITIONS: u16 = 960;
+    // Empty squares taken by the knights, after both bishops and the queen
+    const CHESS960_KNIGHTS: [(usize, usize); 10] = [(0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)];
+
+    // Panics on malformed fens, use try_from_fen for untrusted input
     pub fn from_fen(fen: &str) -> Self {
-        let mut result = Board::empty();
-        let mut file:usize = File::FILE_A.to_usize();
-        let mut rank:usize = Rank::RANK_8.to_usize();
-
-        let mut tokens = fen.split(' ');
-
-        for token in tokens.next().unwrap().chars() {
-            if token == Board::SEPARATOR {
-                file = File::FILE_A.to_usize();
-                rank -= 1;
-            } else if token >= '1' && token <= '8' {
-                let skip = (token as u8 - b'0') as usize;
-                file += skip;
-            } else {
-                let (piece_type, color) = PieceType::from_char(token);
-                result.add_piece(&color, &piece_type, &Square::from_file_rank(&File::FILES[file], &Rank::RANKS[rank]));
-                file += 1;
-            }
+        match Board::try_from_fen(fen) {
+            Ok(board) => board,
+            Err(error) => panic!("invalid fen \"{}\": {}", fen, error),
         }
+    }
+
+    pub fn try_from_fen(fen: &str) -> Result<Self, FenError> {
+        let mut result = Board::empty();
+        let mut tokens = fen.split_whitespace();
+
+        result.parse_pieces(tokens.next().ok_or(FenError::MissingField("piece placement"))?)?;
+        result.compute_king_square();
 
-        let color_to_move = Color::from_string(tokens.next().unwrap());
+        let color_token = tokens.next().ok_or(FenError::MissingField("side to move"))?;
+        let color_to_move = match color_token {
+            "w" => Color::White,
+            "b" => Color::Black,
+            _ => return Err(FenError::InvalidSideToMove(color_token.to_string())),
+        };
 
-        let castling_rights = CastlingRights::from_string(tokens.next().unwrap());
+        let castling_token = tokens.next().ok_or(FenError::MissingField("castling rights"))?;
+        let castling_rights = result.parse_castling_rights(castling_token)?;
 
-        let ep_square = Square::from_string(tokens.next().unwrap());
+        let ep_token = tokens.next().ok_or(FenError::MissingField("en passant square"))?;
+        let ep_square = result.parse_ep_square(ep_token, &color_to_move)?;
 
-        let rule50 = tokens.next();
-        if rule50.is_some() {
-            result.rule_50 = rule50.unwrap().parse().unwrap();
+        if let Some(rule50) = tokens.next() {
+            result.rule_50 = rule50.parse().map_err(|_| FenError::InvalidClock(rule50.to_string()))?;
         }
 
-        let move_number = tokens.next();
-        if move_number.is_some() {
-            result.move_number = max(Color::NUM_COLORS * (move_number.unwrap().parse::<usize>().unwrap().wrapping_sub(Color::Black.to_usize())), 0) as u16 + color_to_move.unwrap().to_u16();
+        if let Some(move_number) = tokens.next() {
+            let move_number = move_number.parse::<usize>().map_err(|_| FenError::InvalidClock(move_number.to_string()))?;
+            result.move_number = (Color::NUM_COLORS * move_number.saturating_sub(1)) as u16 + color_to_move.to_u16();
         }
 
-        result.color_to_move = color_to_move.unwrap();
+        result.color_to_move = color_to_move;
         result.castling_rights = castling_rights;
         result.ep_square = ep_square;
 
-        result.compute_king_square();
-        result.compute_zobrist();
+        // Zobrist computation needs the occupancy set by the initial pass
         result.initial_pass();
+        result.compute_zobrist();
 
-        return result;
+        Ok(result)
+    }
+
+    fn parse_pieces(&mut self, token: &str) -> Result<(), FenError> {
+        let ranks: Vec<&str> = token.split(Board::SEPARATOR).collect();
+        if ranks.len() != Rank::NUM_RANKS {
+            return Err(FenError::InvalidRankCount(ranks.len()));
+        }
+
+        for (index, rank_token) in ranks.iter().enumerate() {
+            let rank = Rank::RANKS[Rank::NUM_RANKS - 1 - index];
+            let mut file: usize = File::FILE_A.to_usize();
+            for c in rank_token.chars() {
+                if ('1'..='8').contains(&c) {
+                    file += (c as u8 - b'0') as usize;
+                } else {
+                    let (piece_type, color) = PieceType::from_char(c);
+                    if piece_type == PieceType::NONE {
+                        return Err(FenError::InvalidPiece(c));
+                    }
+                    if file >= File::NUM_FILES {
+                        return Err(FenError::RankOverflow(rank));
+                    }
+                    self.add_piece(&color, &piece_type, &Square::from_file_rank(&File::FILES[file], &rank));
+                    file += 1;
+                }
+                if file > File::NUM_FILES {
+                    return Err(FenError::RankOverflow(rank));
+                }
+            }
+            if file < File::NUM_FILES {
+                return Err(FenError::RankUnderflow(rank));
+            }
+        }
+
+        for color in Color::COLORS.iter() {
+            if self.piece_bitboard(color, &PieceType::KING).is_empty() {
+                return Err(FenError::MissingKing(*color));
+            }
+        }
+        Ok(())
+    }
+
+    // KQkq use the outermost rook of the side, Shredder-FEN and X-FEN name the rook file instead
+    fn parse_castling_rights(&mut self, token: &str) -> Result<CastlingRights, FenError> {
+        let mut result = CastlingRights::NO_CASTLING;
+        let mut castling_rooks = Vec::new();
+        if token != "-" {
+            for c in token.chars() {
+                let (castling_index, rook_square) = self.parse_castling_rook(c)
+                    .filter(|(castling_index, _)| result.intersect(&CastlingRights::NO_CASTLING.add_index(castling_index)) == CastlingRights::NO_CASTLING)
+                    .ok_or_else(|| FenError::InvalidCastling(token.to_string()))?;
+                result = result.add_index(&castling_index);
+                castling_rooks.push((castling_index, rook_square));
+            }
+        }
+        self.set_castling_rooks(&castling_rooks);
+        Ok(result)
+    }
+
+    // The king has to be on its back rank with the rook on the same rank
+    fn parse_castling_rook(&self, c: char) -> Option<(CastlingIndex, Square)> {
+        let color = if c.is_ascii_uppercase() { Color::White } else { Color::Black };
+        let back_rank = Rank::RANK_1.relative(&color);
+        let king_square = self.king_square(&color);
+        if king_square.to_rank() != back_rank {
+            return None;
+        }
+        let mut rooks = self.piece_bitboard(&color, &PieceType::ROOK).iterator()
+            .filter(|square| square.to_rank() == back_rank);
+        let rook_square = match c.to_ascii_lowercase() {
+            'k' => rooks.filter(|square| square.0 > king_square.0).last(),
+            'q' => rooks.find(|square| square.0 < king_square.0),
+            c => File::from_char(c)
+                .map(|file| Square::from_file_rank(&file, &back_rank))
+                .filter(|square| rooks.any(|rook| rook == *square)),
+        }?;
+        Some((Board::castling_index(&color, &king_square, &rook_square), rook_square))
+    }
+
+    // X-FEN, the rook file is only given when another rook is further out on the same side
+    fn castling_to_fen(&self) -> String {
+        if self.castling_rights == CastlingRights::NO_CASTLING {
+            return "-".to_string();
+        }
+        let mut result = String::with_capacity(CastlingIndex::NUM_INDEXES);
+        for castling_index in self.castling_rights.iterator() {
+            let color = castling_index.color();
+            let rook_square = self.initial_rook_square(&castling_index);
+            let outer_rook = self.piece_bitboard(&color, &PieceType::ROOK).iterator()
+                .filter(|square| square.to_rank() == rook_square.to_rank())
+                .any(|square| match castling_index.side() {
+                    CastlingSide::HSide => square.0 > rook_square.0,
+                    CastlingSide::ASide => square.0 < rook_square.0,
+                });
+            if outer_rook {
+                let file = rook_square.to_file().to_char();
+                result.push(if color == Color::White { file.to_ascii_uppercase() } else { file });
+            } else {
+                result.push(castling_index.to_char());
+            }
+        }
+        result
+    }
+
+    // Scharnagl numbering, 518 is the standard start position
+    pub fn chess960(index: u16) -> Option<Board> {
+        if index >= Board::CHESS960_POSITIONS {
+            return None;
+        }
+        let mut back_rank = [Board::EMPTY_SPACE; File::NUM_FILES];
+        let mut index = index as usize;
+        back_rank[2 * (index % 4) + 1] = 'B';
+        index /= 4;
+        back_rank[2 * (index % 4)] = 'B';
+        index /= 4;
+        Board::place_on_empty(&mut back_rank, index % 6, 'Q');
+        index /= 6;
+        let (first_knight, second_knight) = Board::CHESS960_KNIGHTS[index];
+        Board::place_on_empty(&mut back_rank, second_knight, 'N');
+        Board::place_on_empty(&mut back_rank, first_knight, 'N');
+        for piece in ['R', 'K', 'R'].iter() {
+            Board::place_on_empty(&mut back_rank, 0, *piece);
+        }
+
+        let white: String = back_rank.iter().collect();
+        let fen = format!("{}/pppppppp/8/8/8/8/PPPPPPPP/{} w KQkq - 0 1", white.to_ascii_lowercase(), white);
+        Board::try_from_fen(&fen).ok()
+    }
+
+    fn place_on_empty(back_rank: &mut [char; File::NUM_FILES], empty_index: usize, piece: char) {
+        if let Some(square) = back_rank.iter_mut().filter(|square| **square == Board::EMPTY_SPACE).nth(empty_index) {
+            *square = piece;
+        }
+    }
+
+    // The square must be behind a pawn that just moved two squares
+    fn parse_ep_square(&self, token: &str, color_to_move: &Color) -> Result<Option<Square>, FenError> {
+        if token == "-" {
+            return Ok(None);
+        }
+        let square = Square::from_string(token).ok_or_else(|| FenError::InvalidEnPassant(token.to_string()))?;
+        let their_color = color_to_move.reverse();
+        let square_pawn = square.forward(&their_color);
+        let square_start = square.forward(color_to_move);
+        if square.to_rank() != Rank::RANK_6.relative(color_to_move) ||
+            !self.piece_bitboard(&their_color, &PieceType::PAWN).has(&Bitboard::from_square(&square_pawn)) ||
+            self.color_bitboard(&Color::White).union(&self.color_bitboard(&Color::Black)).has(&Bitboard::from_square(&square).union(&Bitboard::from_square(&square_start))) {
+            return Err(FenError::InvalidEnPassant(token.to_string()));
+        }
+        Ok(Some(square))
     }
 
     pub fn to_fen(&self) -> String {
@@ -97,7 +284,7 @@ impl Board {
         result.push(Board::EMPTY_SPACE);
         result.push(self.color_to_move.to_char());
         result.push(Board::EMPTY_SPACE);
-        result.push_str(self.castling_rights.to_string().as_str());
+        result.push_str(self.castling_to_fen().as_str());
         result.push(Board::EMPTY_SPACE);
         if let Some(ep_square) = self.ep_square {
             result.push_str(ep_square.to_string().as_str());
@@ -107,7 +294,7 @@ impl Board {
         result.push(Board::EMPTY_SPACE);
         result.push_str(self.rule_50.to_string().as_str());
         result.push(Board::EMPTY_SPACE);
-        result.push_str(cmp::max(1, (self.move_number as i16 - self.color_to_move.to_i16()) / 2).to_string().as_str());
+        result.push_str(((self.move_number as i16 - self.color_to_move.to_i16()) / 2 + 1).to_string().as_str());
         return result;
     }
 }
@@ -127,6 +314,90 @@ mod test {
         check_fen(Board::default(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
     }
 
+    #[test]
+    fn zobrist_key() {
+        let board = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(board.zkey, Board::default().zkey);
+        assert_ne!(board.zkey, Board::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 1").zkey);
+    }
+
+    #[test]
+    fn try_from_fen_errors() {
+        let cases = [
+            ("", FenError::MissingField("piece placement")),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR", FenError::MissingField("side to move")),
+            ("rnbqkbnr/pppppppx/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -", FenError::InvalidPiece('x')),
+            ("rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -", FenError::InvalidPiece('9')),
+            ("rnbqkbnr/ppppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -", FenError::RankOverflow(Rank::RANK_7)),
+            ("rnbqkbnr/pppppppp/8/8/8/7P1/PPPPPPP1/RNBQKBNR w KQkq -", FenError::RankOverflow(Rank::RANK_3)),
+            ("rnbqkbnr/pppppppp/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -", FenError::InvalidRankCount(7)),
+            ("rnbqkbnr/ppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -", FenError::RankUnderflow(Rank::RANK_7)),
+            ("rnbqkbnr/pppppppp/8/8/8/7/PPPPPPPP/RNBQKBNR w KQkq -", FenError::RankUnderflow(Rank::RANK_3)),
+            ("rnbqkbnr/pppppppp/8/8//8/PPPPPPPP/RNBQKBNR w KQkq -", FenError::RankUnderflow(Rank::RANK_4)),
+            ("rnbq1bnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQ -", FenError::MissingKing(Color::Black)),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq -", FenError::InvalidSideToMove("x".to_string())),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkx -", FenError::InvalidCastling("KQkx".to_string())),
+            ("rnbqkbn1/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -", FenError::InvalidCastling("KQkq".to_string())),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPKPPP/RNBQ1BNR w KQkq -", FenError::InvalidCastling("KQkq".to_string())),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkqK -", FenError::InvalidCastling("KQkqK".to_string())),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w GQkq -", FenError::InvalidCastling("GQkq".to_string())),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/1NBQKBNR w KQkq -", FenError::InvalidCastling("KQkq".to_string())),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e3", FenError::InvalidEnPassant("e3".to_string())),
+            ("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e6", FenError::InvalidEnPassant("e6".to_string())),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq z9", FenError::InvalidEnPassant("z9".to_string())),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - x 1", FenError::InvalidClock("x".to_string())),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 -1", FenError::InvalidClock("-1".to_string())),
+        ];
+        for (fen, error) in cases.iter() {
+            assert_eq!(Board::try_from_fen(fen).unwrap_err(), *error, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn try_from_fen_valid() {
+        let board = Board::try_from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1").unwrap();
+        assert_eq!(board.ep_square, Some(Square::E3));
+        assert_eq!(Board::try_from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap(), Board::default());
+    }
+
+    #[test]
+    #[should_panic]
+    fn from_fen_panics() {
+        Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq -");
+    }
+
+    #[test]
+    fn move_number() {
+        let fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 12";
+        assert_eq!(Board::from_fen(fen).to_fen(), fen);
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 3 40";
+        assert_eq!(Board::from_fen(fen).to_fen(), fen);
+        assert_eq!(Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 0").to_fen(), "4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+    }
+
+    #[test]
+    fn chess960_castling() {
+        let fen = "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9";
+        let board = Board::from_fen(fen);
+        assert_eq!(board.initial_rook_square(&CastlingIndex::WhiteH), Square::H1);
+        assert_eq!(board.initial_rook_square(&CastlingIndex::WhiteA), Square::F1);
+        assert_eq!(board.to_fen(), "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w KQkq - 2 9");
+        assert_eq!(Board::from_fen(&board.to_fen()), board);
+
+        // A rook further out needs the inner one to be named by its file
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/RR2K2R w BK - 0 1");
+        assert_eq!(board.initial_rook_square(&CastlingIndex::WhiteA), Square::B1);
+        assert_eq!(board.to_fen(), "4k3/8/8/8/8/8/8/RR2K2R w KB - 0 1");
+    }
+
+    #[test]
+    fn chess960() {
+        assert_eq!(Board::chess960(518).unwrap(), Board::default());
+        assert_eq!(Board::chess960(0).unwrap().to_fen(), "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1");
+        assert_eq!(Board::chess960(959).unwrap().to_fen(), "rkrnnqbb/pppppppp/8/8/8/8/PPPPPPPP/RKRNNQBB w KQkq - 0 1");
+        assert!(Board::chess960(Board::CHESS960_POSITIONS).is_none());
+    }
+
     #[test]
     fn partial_fen() {
         assert_eq!(Board::default(), Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -"));
@@ -0,0 +1,20 @@
+use crate::advanced::zobrist_key::ZobristKey;
+use crate::types::bitboard::Bitboard;
+use crate::types::castling_rights::CastlingRights;
+use crate::types::color::Color;
+use crate::types::piece_type::PieceType;
+use crate::types::square::Square;
+
+// Irreversible information needed to undo a move
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct BoardState {
+    pub piece_captured: PieceType,
+    pub castling_rights: CastlingRights,
+    pub ep_square: Option<Square>,
+    pub rule_50: u16,
+    pub zkey: ZobristKey,
+    pub zkey_pawn: ZobristKey,
+    pub pinned_bitboard: Bitboard,
+    pub check_bitboard: Bitboard,
+    pub danger_bitboard: [[Bitboard; PieceType::NUM_PIECE_TYPES]; Color::NUM_COLORS],
+}
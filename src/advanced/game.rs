@@ -1 +1,248 @@
-pub const GAME_MAX_LENGTH: usize = 4095;
\ No newline at end of file
+use crate::advanced::attack_info::AttackInfo;
+use crate::advanced::board::Board;
+use crate::advanced::move_list::MoveList;
+use crate::advanced::zobrist_key::ZobristKey;
+use crate::types::bitboard::Bitboard;
+use crate::types::board_move::BoardMove;
+use crate::types::color::Color;
+use crate::types::piece_type::PieceType;
+
+pub const GAME_MAX_LENGTH: usize = 4095;
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum DrawReason {
+    Stalemate,
+    InsufficientMaterial,
+    ThreefoldRepetition,
+    FivefoldRepetition,
+    FiftyMoveRule,
+    SeventyFiveMoveRule,
+}
+
+impl DrawReason {
+    // Claimable draws only end the game when a player asks for it
+    #[inline]
+    pub fn is_claimable(&self) -> bool {
+        *self == DrawReason::ThreefoldRepetition || *self == DrawReason::FiftyMoveRule
+    }
+}
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum GameResult {
+    Ongoing,
+    // Holds the winner
+    Checkmate(Color),
+    Draw(DrawReason),
+}
+
+pub struct Game {
+    board: Board,
+    moves: Vec<BoardMove>,
+    // Key of every position reached, the current one included
+    keys: Vec<ZobristKey>,
+    move_list: MoveList,
+    attack_info: AttackInfo,
+}
+
+impl Game {
+    pub const FIFTY_MOVE_PLIES: u16 = 100;
+    pub const SEVENTY_FIVE_MOVE_PLIES: u16 = 150;
+    pub const THREEFOLD: usize = 3;
+    pub const FIVEFOLD: usize = 5;
+
+    pub fn new() -> Self {
+        Game::from_board(Board::default())
+    }
+
+    pub fn from_board(board: Board) -> Self {
+        Game {
+            keys: vec![board.zkey],
+            board,
+            moves: Vec::new(),
+            move_list: MoveList::new(),
+            attack_info: AttackInfo::new(),
+        }
+    }
+
+    #[inline]
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    #[inline]
+    pub fn moves(&self) -> &[BoardMove] {
+        &self.moves
+    }
+
+    // Illegal moves are rejected and leave the game untouched
+    pub fn do_move(&mut self, board_move: &BoardMove) -> bool {
+        if !self.board.legal_moves(&mut self.move_list, &mut self.attack_info).contains(board_move) {
+            return false;
+        }
+        self.board.do_move(board_move);
+        self.moves.push(*board_move);
+        self.keys.push(self.board.zkey);
+        true
+    }
+
+    pub fn undo_move(&mut self) -> Option<BoardMove> {
+        let board_move = self.moves.pop()?;
+        self.board.undo_move(&board_move);
+        self.keys.pop();
+        Some(board_move)
+    }
+
+    // Times the current position was reached, only positions since the last irreversible move can repeat
+    pub fn repetition_count(&self) -> usize {
+        let current = self.board.zkey;
+        let window = (self.board.rule_50 as usize).min(self.keys.len() - 1);
+        self.keys[self.keys.len() - 1 - window..].iter()
+            .rev()
+            .step_by(2)
+            .filter(|&&key| key == current)
+            .count()
+    }
+
+    pub fn result(&mut self) -> GameResult {
+        if self.board.legal_moves(&mut self.move_list, &mut self.attack_info).is_empty() {
+            return if self.board.check_bitboard.is_not_empty() {
+                GameResult::Checkmate(self.board.color_to_move.reverse())
+            } else {
+                GameResult::Draw(DrawReason::Stalemate)
+            };
+        }
+        if self.board.is_insufficient_material() {
+            return GameResult::Draw(DrawReason::InsufficientMaterial);
+        }
+
+        let repetitions = self.repetition_count();
+        if repetitions >= Game::FIVEFOLD {
+            GameResult::Draw(DrawReason::FivefoldRepetition)
+        } else if self.board.rule_50 >= Game::SEVENTY_FIVE_MOVE_PLIES {
+            GameResult::Draw(DrawReason::SeventyFiveMoveRule)
+        } else if repetitions >= Game::THREEFOLD {
+            GameResult::Draw(DrawReason::ThreefoldRepetition)
+        } else if self.board.rule_50 >= Game::FIFTY_MOVE_PLIES {
+            GameResult::Draw(DrawReason::FiftyMoveRule)
+        } else {
+            GameResult::Ongoing
+        }
+    }
+}
+
+impl Board {
+    // Neither side can mate, a lone minor piece or bishops on a single square color
+    pub fn is_insufficient_material(&self) -> bool {
+        let heavy_pieces = self.piece_type_bitboard(&PieceType::PAWN)
+            .union(&self.piece_type_bitboard(&PieceType::ROOK))
+            .union(&self.piece_type_bitboard(&PieceType::QUEEN));
+        if heavy_pieces.is_not_empty() {
+            return false;
+        }
+
+        let knights = self.piece_type_bitboard(&PieceType::KNIGHT);
+        let bishops = self.piece_type_bitboard(&PieceType::BISHOP);
+        if knights.union(&bishops).count() <= 1 {
+            return true;
+        }
+        knights.is_empty() &&
+            (bishops.intersect(&Bitboard::DARK_SQUARES) == bishops || bishops.intersect(&Bitboard::DARK_SQUARES).is_empty())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::square::Square;
+
+    fn play(game: &mut Game, moves: &[&str]) {
+        for move_str in moves.iter() {
+            let board_move = game.board().legal_moves(&mut MoveList::new(), &mut AttackInfo::new()).iter()
+                .copied()
+                .find(|board_move| board_move.to_string() == *move_str)
+                .unwrap();
+            assert!(game.do_move(&board_move));
+        }
+    }
+
+    #[test]
+    fn checkmate() {
+        let mut game = Game::new();
+        play(&mut game, &["f2f3", "e7e5", "g2g4"]);
+        assert_eq!(game.result(), GameResult::Ongoing);
+        play(&mut game, &["d8h4"]);
+        assert_eq!(game.result(), GameResult::Checkmate(Color::Black));
+    }
+
+    #[test]
+    fn stalemate() {
+        let mut game = Game::from_board(Board::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - -"));
+        assert_eq!(game.result(), GameResult::Draw(DrawReason::Stalemate));
+    }
+
+    #[test]
+    fn rejects_illegal_moves() {
+        let mut game = Game::new();
+        assert!(!game.do_move(&BoardMove::build_normal(&Square::E2, &Square::E5)));
+        assert!(game.moves().is_empty());
+        assert_eq!(game.board().to_fen(), Board::default().to_fen());
+    }
+
+    #[test]
+    fn repetitions() {
+        let mut game = Game::new();
+        let shuffle = ["g1f3", "g8f6", "f3g1", "f6g8"];
+        play(&mut game, &shuffle);
+        assert_eq!(game.repetition_count(), 2);
+        assert_eq!(game.result(), GameResult::Ongoing);
+        play(&mut game, &shuffle);
+        assert_eq!(game.result(), GameResult::Draw(DrawReason::ThreefoldRepetition));
+        assert!(DrawReason::ThreefoldRepetition.is_claimable());
+        play(&mut game, &shuffle);
+        play(&mut game, &shuffle);
+        assert_eq!(game.result(), GameResult::Draw(DrawReason::FivefoldRepetition));
+        assert!(!DrawReason::FivefoldRepetition.is_claimable());
+
+        game.undo_move();
+        assert_eq!(game.repetition_count(), 4);
+        assert_eq!(game.moves().len(), 15);
+    }
+
+    #[test]
+    fn irreversible_moves_reset_repetitions() {
+        let mut game = Game::new();
+        play(&mut game, &["g1f3", "g8f6", "f3g1", "f6g8", "e2e4", "e7e5"]);
+        play(&mut game, &["g1f3", "g8f6", "f3g1", "f6g8"]);
+        assert_eq!(game.repetition_count(), 2);
+    }
+
+    #[test]
+    fn move_rules() {
+        let mut game = Game::from_board(Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 99 80"));
+        assert_eq!(game.result(), GameResult::Ongoing);
+        play(&mut game, &["a1a2"]);
+        assert_eq!(game.result(), GameResult::Draw(DrawReason::FiftyMoveRule));
+
+        let mut game = Game::from_board(Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 149 80"));
+        play(&mut game, &["a1a2"]);
+        assert_eq!(game.result(), GameResult::Draw(DrawReason::SeventyFiveMoveRule));
+
+        // Checkmate on the last move wins
+        let mut game = Game::from_board(Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 99 80"));
+        play(&mut game, &["a1a8"]);
+        assert_eq!(game.result(), GameResult::Checkmate(Color::White));
+    }
+
+    #[test]
+    fn insufficient_material() {
+        assert!(Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - -").is_insufficient_material());
+        assert!(Board::from_fen("4k3/8/8/8/8/8/8/2N1K3 w - -").is_insufficient_material());
+        assert!(Board::from_fen("4k3/8/8/8/8/8/8/2B1K3 w - -").is_insufficient_material());
+        assert!(Board::from_fen("2b1k3/8/8/8/8/8/8/4KB2 w - -").is_insufficient_material());
+        assert!(!Board::from_fen("1b2k3/8/8/8/8/8/8/4KB2 w - -").is_insufficient_material());
+        assert!(!Board::from_fen("4k3/8/8/8/8/8/8/1NN1K3 w - -").is_insufficient_material());
+        assert!(!Board::from_fen("4k3/8/8/8/8/8/P7/4K3 w - -").is_insufficient_material());
+        let mut game = Game::from_board(Board::from_fen("4k3/8/8/8/8/8/8/2N1K3 w - -"));
+        assert_eq!(game.result(), GameResult::Draw(DrawReason::InsufficientMaterial));
+    }
+}
@@ -2,8 +2,12 @@ pub mod attack_info;
 pub mod bitboard_move;
 pub mod board;
 pub mod board_factory;
+pub mod board_state;
 pub mod game;
 pub mod move_list;
 pub mod move_generation;
 pub mod perft;
+pub mod san;
+pub mod see;
+pub mod validation;
 pub mod zobrist_key;
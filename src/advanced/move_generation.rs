@@ -29,6 +29,7 @@ impl MoveList {
         self.generate_moves(board, attack_info, &PieceType::KING, &board.empty_bitboard());
     }
 
+    // Chess960 king and rook may start anywhere on the back rank, only they can be in their way
     #[inline]
     fn generate_castling_moves(&mut self, board: &Board, attack_info: &AttackInfo) {
         let our_color = board.color_to_move;
@@ -42,11 +43,16 @@ impl MoveList {
 
             let rook_from = board.initial_rook_square(&castling_index);
             let rook_to = castling_index.square_rook_to();
-            let rook_path = rook_from.between(&rook_to);
-
-            if king_path.union(&rook_path).intersect(&board.game_bitboard())
-                .union(&king_path.intersect(&attack_info.all_attack_bitboard(&their_color, &PieceType::NONE))).is_empty() {
-                let board_move = BoardMove::build_castling(&king_square, &king_to);
+            let rook_path = rook_from.between(&rook_to)
+                .union(&Bitboard::from_square(&rook_to));
+
+            let occupied = board.game_bitboard().difference(&Bitboard::from_square(&rook_from));
+            let blockers = occupied.difference(&Bitboard::from_square(&king_square));
+            if king_path.union(&rook_path).intersect(&blockers)
+                .union(&king_path.intersect(&attack_info.all_attack_bitboard(&their_color, &PieceType::NONE))).is_empty() &&
+                // The castling rook may be hiding an attack on the king destination
+                board.attackers_to(&king_to, &occupied).intersect(&board.rook_like_pieces(&their_color)).is_empty() {
+                let board_move = BoardMove::build_castling(&king_square, &rook_from);
                 self.add_move(board_move);
             }
         }
@@ -96,6 +102,13 @@ impl MoveList {
         }
     }
 
+    // Pins, check masks and king danger squares are applied, so every move generated is legal
+    #[inline]
+    pub fn generate_legal(&mut self, board: &Board, attack_info: &mut AttackInfo) {
+        self.generate_noisy(board, attack_info);
+        self.generate_quiets(board, attack_info);
+    }
+
     #[inline]
     pub fn generate_noisy(&mut self, board: &Board, attack_info: &mut AttackInfo) {
         attack_info.update(board);
@@ -136,7 +149,7 @@ impl MoveList {
                     let king_square = board.king_square(&color);
                     bitboard_to = bitboard_to.intersect(&king_square.pinned_mask(&square));
                 }
-                if bitboard_to.is_not_empty() {
+                if bitboard_to.is_not_empty() && !ep_discovers_check(board, &square, &ep_square) {
                     let board_move = BoardMove::build_passant(&square, &ep_square);
                     self.add_move(board_move);
                 }
@@ -216,12 +229,36 @@ impl MoveList {
     }
 }
 
+impl Board {
+    // Replaces the contents of the move list, the moves are only valid until it is used again
+    pub fn legal_moves<'a>(&self, move_list: &'a mut MoveList, attack_info: &mut AttackInfo) -> &'a [BoardMove] {
+        move_list.clear();
+        move_list.generate_legal(self, attack_info);
+        move_list.moves()
+    }
+}
+
+// Both pawns leave the rank when capturing en passant, which can expose the king
+#[inline]
+fn ep_discovers_check(board: &Board, square_from: &Square, ep_square: &Square) -> bool {
+    let color = board.color_to_move;
+    let their_color = color.reverse();
+    let king_square = board.king_square(&color);
+    let occupied = board.game_bitboard()
+        .difference(&Bitboard::from_square(square_from))
+        .difference(&Bitboard::from_square(&ep_square.forward(&their_color)))
+        .union(&Bitboard::from_square(ep_square));
+    king_square.rook_moves(&occupied).intersect(&board.rook_like_pieces(&their_color))
+        .union(&king_square.bishop_moves(&occupied).intersect(&board.bishop_like_pieces(&their_color)))
+        .is_not_empty()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
     fn count_moves(fen: &str) -> u32 {
-        let board = Board::from_fen(fen);
+        let mut board = Board::from_fen(fen);
         let mut move_list = MoveList::new();
         let mut attack_info = AttackInfo::new();
         move_list.generate_quiets(&board, &mut attack_info);
@@ -229,12 +266,13 @@ mod test {
         let mut legal_moves = 0;
         while move_list.has_next() {
             let board_move = move_list.next();
-            if board.clone().do_move(&board_move) {
+            if board.do_move(&board_move) {
                 println!("move {}", board_move.to_string());
                 legal_moves += 1;
             } else {
                 println!("Invalid {}", board_move.to_string());
             }
+            board.undo_move(&board_move);
         }
         return legal_moves;
     }
@@ -426,6 +464,48 @@ mod test {
         assert_eq!(legal_moves, 7)
     }
 
+    #[test]
+    fn ep_horizontal_pin_capture() {
+        let legal_moves = count_moves("8/8/8/KP1p3r/1R3pPk/4P3/8/8 b - g3");
+        assert_eq!(legal_moves, 13)
+    }
+
+    #[test]
+    fn generate_legal() {
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - -",
+            "8/8/1k6/2b5/2pP4/8/5K2/8 b - d3",
+            "8/8/8/KP1p3r/1R3pPk/4P3/8/8 b - g3",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        ];
+        for fen in fens.iter() {
+            let mut board = Board::from_fen(fen);
+            let mut move_list = MoveList::new();
+            let mut attack_info = AttackInfo::new();
+            move_list.generate_legal(&board, &mut attack_info);
+            while move_list.has_next() {
+                let board_move = move_list.next();
+                assert!(board.do_move(&board_move), "{} {}", fen, board_move.to_string());
+                board.undo_move(&board_move);
+            }
+        }
+    }
+
+    #[test]
+    fn legal_moves() {
+        let mut move_list = MoveList::new();
+        let mut attack_info = AttackInfo::new();
+        assert_eq!(Board::default().legal_moves(&mut move_list, &mut attack_info).len(), 20);
+        let kiwipete = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -");
+        assert_eq!(kiwipete.legal_moves(&mut move_list, &mut attack_info).len(), 48);
+        // The list is reused from scratch even when a ply is in progress
+        move_list.start_ply();
+        move_list.add_move(BoardMove::build_normal(&Square::A1, &Square::A2));
+        assert_eq!(kiwipete.legal_moves(&mut move_list, &mut attack_info).len(), 48);
+        assert!(Board::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - -").legal_moves(&mut move_list, &mut attack_info).is_empty());
+    }
+
     #[test]
     fn p4_issue() {
         let legal_moves = count_moves("r1bqkbnr/pppppppp/2n5/8/Q7/2P5/PP1PPPPP/RNB1KBNR b KQkq -");
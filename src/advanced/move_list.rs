@@ -38,7 +38,37 @@ impl MoveList {
         self.current_ply -= 1;
     }
 
+    // Drops every ply, the list can be reused from scratch
+    pub fn clear(&mut self) {
+        self.current_ply = 0;
+        self.next_to_move[0] = 0;
+        self.next_to_generate[0] = 0;
+    }
+
+    // Moves not yet returned by next in the current ply, in generation order
+    pub fn moves(&self) -> &[BoardMove] {
+        &self.move_list[self.next_to_move[self.current_ply]..self.next_to_generate[self.current_ply]]
+    }
+
     pub fn next(&mut self) -> BoardMove {
+        let best_index = self.best_index();
+        self.take(best_index)
+    }
+
+    // Picks the best move left, unless its score is below the minimum
+    pub fn next_above(&mut self, min_score: u64) -> Option<BoardMove> {
+        if !self.has_next() {
+            return None;
+        }
+        let best_index = self.best_index();
+        if self.move_score[best_index] < min_score {
+            return None;
+        }
+        Some(self.take(best_index))
+    }
+
+    #[inline]
+    fn best_index(&self) -> usize {
         let start_index = self.next_to_move[self.current_ply];
         let end_index = self.next_to_generate[self.current_ply];
         let mut best_index = start_index;
@@ -47,6 +77,12 @@ impl MoveList {
                 best_index = index;
             }
         }
+        best_index
+    }
+
+    #[inline]
+    fn take(&mut self, best_index: usize) -> BoardMove {
+        let start_index = self.next_to_move[self.current_ply];
         let best_move = self.move_list[best_index];
         self.move_list[best_index] = self.move_list[start_index];
         self.move_score[best_index] = self.move_score[start_index];
@@ -58,11 +94,31 @@ impl MoveList {
         return self.next_to_generate[self.current_ply] != self.next_to_move[self.current_ply];
     }
 
+    // Moves not yet returned by next in the current ply
+    pub fn remaining_moves(&self) -> usize {
+        self.next_to_generate[self.current_ply] - self.next_to_move[self.current_ply]
+    }
+
     pub fn add_move(&mut self, board_move: BoardMove) {
-        self.move_list[self.next_to_generate[self.current_ply]] = board_move;
+        let index = self.next_to_generate[self.current_ply];
+        self.move_list[index] = board_move;
+        self.move_score[index] = 0;
         self.next_to_generate[self.current_ply] += 1;
     }
 
+    // Moves added from now on start at this index
+    #[inline]
+    pub fn generated_index(&self) -> usize {
+        self.next_to_generate[self.current_ply]
+    }
+
+    // Scores the moves generated since the index, higher scores are returned first
+    pub fn score_moves<F: FnMut(&BoardMove) -> u64>(&mut self, start_index: usize, mut scorer: F) {
+        for index in start_index..self.next_to_generate[self.current_ply] {
+            self.move_score[index] = scorer(&self.move_list[index]);
+        }
+    }
+
     pub fn skip_moves(&mut self) {
         self.next_to_move[self.current_ply] = self.next_to_generate[self.current_ply];
     }
@@ -115,6 +171,36 @@ mod test {
         assert_eq!(move_list.has_next(), true);
     }
 
+    #[test]
+    fn remaining_moves() {
+        let mut move_list = MoveList::new();
+        move_list.start_ply();
+        assert_eq!(move_list.remaining_moves(), 0);
+        move_list.add_move(BoardMove::build_normal(&Square::A1, &Square::A2));
+        move_list.add_move(BoardMove::build_normal(&Square::A2, &Square::A1));
+        assert_eq!(move_list.remaining_moves(), 2);
+        move_list.next();
+        assert_eq!(move_list.remaining_moves(), 1);
+    }
+
+    #[test]
+    fn clear_moves() {
+        let mut move_list = MoveList::new();
+        let first = BoardMove::build_normal(&Square::A1, &Square::A2);
+        let second = BoardMove::build_normal(&Square::A2, &Square::A3);
+        move_list.add_move(first);
+        move_list.start_ply();
+        move_list.add_move(second);
+        assert_eq!(move_list.moves(), &[second]);
+        move_list.clear();
+        assert_eq!(move_list.current_ply, 0);
+        assert!(move_list.moves().is_empty());
+        move_list.add_move(second);
+        move_list.add_move(first);
+        move_list.next();
+        assert_eq!(move_list.moves(), &[first]);
+    }
+
     #[test]
     fn skip_moves() {
         let mut move_list = MoveList::new();
@@ -133,4 +219,24 @@ mod test {
         move_list.skip_moves();
         assert_eq!(move_list.has_next(), false);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn scored_moves() {
+        let mut move_list = MoveList::new();
+        move_list.start_ply();
+        let first = BoardMove::build_normal(&Square::A1, &Square::A2);
+        let second = BoardMove::build_normal(&Square::A2, &Square::A3);
+        let third = BoardMove::build_normal(&Square::A3, &Square::A4);
+        move_list.add_move(first);
+        move_list.add_move(second);
+        move_list.score_moves(0, |board_move| if *board_move == second { 10 } else { 1 });
+        let start_index = move_list.generated_index();
+        move_list.add_move(third);
+        move_list.score_moves(start_index, |_| 5);
+        assert_eq!(move_list.next_above(6), Some(second));
+        assert_eq!(move_list.next_above(6), None);
+        assert_eq!(move_list.next(), third);
+        assert_eq!(move_list.next_above(0), Some(first));
+        assert_eq!(move_list.next_above(0), None);
+    }
+}
@@ -1,115 +1,289 @@
+use std::mem::size_of;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
 use crate::advanced::attack_info::AttackInfo;
 use crate::advanced::board::Board;
 use crate::advanced::move_list::MoveList;
+use crate::types::board_move::BoardMove;
+
+#[derive(Copy, Clone)]
+struct PerftEntry {
+    key: u64,
+    nodes: u64,
+    depth: u8,
+}
+
+impl PerftEntry {
+    const EMPTY: PerftEntry = PerftEntry {
+        key: 0,
+        nodes: 0,
+        depth: 0,
+    };
+}
 
 pub struct Perft {
     attack_info: AttackInfo,
     move_list: MoveList,
-    invalid_moves: u64,
-    valid_moves: u64
+    hash: Vec<PerftEntry>,
 }
 
 impl Perft {
+    // Positions handed out per thread, so threads that finish early can take more work
+    const TASKS_PER_THREAD: usize = 16;
+
     pub fn new() -> Self {
         Self {
             attack_info: AttackInfo::new(),
             move_list: MoveList::new(),
-            invalid_moves: 0,
-            valid_moves: 0,
+            hash: Vec::new(),
+        }
+    }
+
+    // Subtree counts are cached by zobrist key and depth, entries are rounded down to a power of two
+    pub fn with_hash(size_mb: usize) -> Self {
+        let mut result = Perft::new();
+        if size_mb > 0 {
+            let entries = size_mb * 1024 * 1024 / size_of::<PerftEntry>();
+            let entries = if entries.is_power_of_two() {
+                entries
+            } else {
+                entries.next_power_of_two() / 2
+            };
+            result.hash = vec![PerftEntry::EMPTY; entries];
         }
+        result
     }
 
-    pub fn divide(&mut self, board: &mut Board, depth: u8) {
+    pub fn divide(&mut self, board: &mut Board, depth: u8) -> Vec<(BoardMove, u64)> {
+        let mut result = Vec::new();
         if depth == 0 {
-            return;
+            return result;
         }
         self.move_list.start_ply();
-        self.move_list.generate_quiets(board, &mut self.attack_info);
-        self.move_list.generate_noisy(board, &mut self.attack_info);
+        self.move_list.generate_legal(board, &mut self.attack_info);
 
         while self.move_list.has_next() {
             let board_move = self.move_list.next();
-            let mut clone = board.clone();
-            if clone.do_move(&board_move) {
-                println!("{} -> {}", board_move.to_string(), self.perft(&mut clone, depth - 1));
-            }
+            board.do_move(&board_move);
+            result.push((board_move, self.perft(board, depth - 1)));
+            board.undo_move(&board_move);
         }
         self.move_list.end_ply();
+        result
+    }
+
+    // Same counts as divide, each thread owns a Perft and the hash is split between them
+    pub fn divide_parallel(board: &Board, depth: u8, threads: usize, hash_mb: usize) -> Vec<(BoardMove, u64)> {
+        if depth == 0 {
+            return Vec::new();
+        }
+        let threads = threads.max(1);
+        let mut perft = Perft::new();
+        let root_moves = perft.legal_moves(board);
+        let mut tasks: Vec<(usize, Board)> = Vec::new();
+        for (index, board_move) in root_moves.iter().enumerate() {
+            let mut task_board = board.clone_position();
+            task_board.do_move(board_move);
+            tasks.push((index, task_board));
+        }
+        // Split deeper when there are not enough root moves to keep every thread busy
+        let mut task_depth = depth - 1;
+        while tasks.len() < threads * Perft::TASKS_PER_THREAD && task_depth > 1 {
+            let mut next_tasks = Vec::new();
+            for (index, task_board) in tasks.iter() {
+                for board_move in perft.legal_moves(task_board) {
+                    let mut next_board = task_board.clone_position();
+                    next_board.do_move(&board_move);
+                    next_tasks.push((*index, next_board));
+                }
+            }
+            tasks = next_tasks;
+            task_depth -= 1;
+        }
+
+        let next_task = AtomicUsize::new(0);
+        let mut result: Vec<(BoardMove, u64)> = root_moves.iter().map(|board_move| (*board_move, 0)).collect();
+        thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads).map(|_| scope.spawn(|| {
+                let mut perft = Perft::with_hash(hash_mb / threads);
+                let mut counts = vec![0; root_moves.len()];
+                loop {
+                    let task_index = next_task.fetch_add(1, Ordering::Relaxed);
+                    if task_index >= tasks.len() {
+                        break;
+                    }
+                    let (index, task_board) = &tasks[task_index];
+                    counts[*index] += perft.perft(&mut task_board.clone_position(), task_depth);
+                }
+                counts
+            })).collect();
+            for worker in workers {
+                let counts = worker.join().expect("perft thread panicked");
+                for ((_, nodes), count) in result.iter_mut().zip(counts) {
+                    *nodes += count;
+                }
+            }
+        });
+        result
     }
 
     pub fn perft(&mut self, board: &mut Board, depth: u8) -> u64 {
         if depth == 0 {
             return 1;
         }
-        self.move_list.start_ply();
-        self.move_list.generate_quiets(board, &mut self.attack_info);
-        self.move_list.generate_noisy(board, &mut self.attack_info);
+        if let Some(nodes) = self.probe(board, depth) {
+            return nodes;
+        }
 
-        let mut result = 0;
+        self.move_list.start_ply();
+        self.move_list.generate_legal(board, &mut self.attack_info);
 
-        while self.move_list.has_next() {
-            let board_move = self.move_list.next();
-            let mut clone = board.clone();
-            if clone.do_move(&board_move) {
-                result += self.perft(&mut clone, depth - 1);
-                self.valid_moves += 1;
-            } else {
-                self.invalid_moves += 1;
+        // Moves are legal, so the last ply only needs to be counted
+        let result = if depth == 1 {
+            self.move_list.remaining_moves() as u64
+        } else {
+            let mut nodes = 0;
+            while self.move_list.has_next() {
+                let board_move = self.move_list.next();
+                board.do_move(&board_move);
+                nodes += self.perft(board, depth - 1);
+                board.undo_move(&board_move);
             }
-        }
+            nodes
+        };
         self.move_list.end_ply();
 
-        return result;
+        self.store(board, depth, result);
+        result
+    }
+
+    fn legal_moves(&mut self, board: &Board) -> Vec<BoardMove> {
+        board.legal_moves(&mut self.move_list, &mut self.attack_info).to_vec()
+    }
+
+    #[inline]
+    fn probe(&self, board: &Board, depth: u8) -> Option<u64> {
+        if self.hash.is_empty() || depth < 2 {
+            return None;
+        }
+        let entry = &self.hash[board.zkey.to_u64() as usize & (self.hash.len() - 1)];
+        if entry.key == board.zkey.to_u64() && entry.depth == depth {
+            Some(entry.nodes)
+        } else {
+            None
+        }
     }
-}
 
+    #[inline]
+    fn store(&mut self, board: &Board, depth: u8, nodes: u64) {
+        if self.hash.is_empty() || depth < 2 {
+            return;
+        }
+        let index = board.zkey.to_u64() as usize & (self.hash.len() - 1);
+        self.hash[index] = PerftEntry {
+            key: board.zkey.to_u64(),
+            nodes,
+            depth,
+        };
+    }
+}
 
+// Splits the work across root moves, or deeper when there are few of them
+pub fn perft_parallel(board: &Board, depth: u8, threads: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    Perft::divide_parallel(board, depth, threads, 0).iter().map(|(_, nodes)| nodes).sum()
+}
 
 #[cfg(test)]
 mod test {
+    use std::env;
     use std::fs::File;
     use std::io::{BufReader, BufRead};
     use crate::advanced::board::Board;
-    use crate::advanced::perft::Perft;
-    use crate::types::board_move::BoardMove;
-    use crate::types::square::Square;
+    use crate::advanced::perft::{Perft, perft_parallel};
+    use crate::epd::epd_position::EpdPosition;
+
+    const DEFAULT_DEPTH_LIMIT: u8 = 4;
 
     fn check_perft_file(path: &str, depth_limit: u8) {
         let file = File::open(path).expect("failed to open test suite");
         let reader = BufReader::new(file);
-
-        let mut board = Board::default();
         let mut perft = Perft::new();
 
         for line in reader.lines().map(|l| l.unwrap()) {
-            let mut slices = line.trim().splitn(2, ' ');
-
-            match slices.next() {
-                Some("epd") => {
-                    let position = slices.next().expect("expected position");
-                    println!("position {}", position);
-                    board = Board::from_fen(position);
+            let position = EpdPosition::parse(&line).expect("invalid epd");
+            println!("position {}", position.id().unwrap_or(&line));
+            let mut board = position.board.clone();
+            for (depth, nodes) in position.perft_counts().expect("invalid perft counts") {
+                if depth <= depth_limit {
+                    assert_eq!(perft.perft(&mut board, depth), nodes);
                 }
-                Some("perft") => {
-                    let mut params = slices.next().expect("expected perft params").splitn(2, ' ');
-                    let depth: u8 = params.next().expect("expected perft depth").parse().expect("expected integer value");
+            }
+        }
+    }
+
+    #[test]
+    fn start_position() {
+        let mut board = Board::default();
+        let mut perft = Perft::new();
+        assert_eq!(perft.perft(&mut board, 1), 20);
+        assert_eq!(perft.perft(&mut board, 4), 197_281);
+    }
 
-                    let nodes: u64 = params.next().expect("expected perft nodes").parse().expect("expected integer value");
+    #[test]
+    fn hash_keeps_counts() {
+        let mut board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -");
+        let mut perft = Perft::with_hash(1);
+        assert_eq!(perft.perft(&mut board, 3), 97_862);
+        assert_eq!(perft.perft(&mut board, 3), 97_862);
+        assert_eq!(perft.perft(&mut board, 4), 4_085_603);
+    }
 
-                    if depth <= depth_limit {
-                        assert_eq!(perft.perft(&mut board, depth), nodes);
-                    }
-                }
-                _ => {}
-            }
+    #[test]
+    fn divide() {
+        let mut board = Board::default();
+        let mut perft = Perft::new();
+        let divide = perft.divide(&mut board, 3);
+        assert_eq!(divide.len(), 20);
+        assert_eq!(divide.iter().map(|(_, nodes)| nodes).sum::<u64>(), 8_902);
+        let (_, nodes) = divide.iter().find(|(board_move, _)| board_move.to_string() == "e2e4").unwrap();
+        assert_eq!(*nodes, 600);
+    }
+
+    #[test]
+    fn parallel() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -";
+        let mut board = Board::from_fen(fen);
+        let mut perft = Perft::new();
+        for threads in [1, 3, 8].iter() {
+            assert_eq!(perft_parallel(&board, 3, *threads), 97_862);
         }
-        println!("valid nodes {}", perft.valid_moves);
-        println!("invalid nodes {}", perft.invalid_moves);
+        assert_eq!(Perft::divide_parallel(&board, 3, 4, 4), perft.divide(&mut board, 3));
+        assert_eq!(perft_parallel(&board, 1, 4), 48);
+        assert_eq!(perft_parallel(&board, 0, 4), 1);
+
+        // Only two root moves, the work is split further down the tree
+        let mut board = Board::from_fen("4k3/8/8/8/8/8/r7/K7 w - - 0 1");
+        assert_eq!(perft_parallel(&board, 5, 4), perft.perft(&mut board, 5));
+    }
+
+    // Deeper runs are enabled with URUBU_PERFT_DEPTH, e.g. URUBU_PERFT_DEPTH=7 cargo test perft_suite
+    fn depth_limit() -> u8 {
+        env::var("URUBU_PERFT_DEPTH").ok()
+            .and_then(|depth| depth.parse().ok())
+            .unwrap_or(DEFAULT_DEPTH_LIMIT)
+    }
+
+    #[test]
+    fn perft_suite() {
+        check_perft_file(concat!(env!("CARGO_MANIFEST_DIR"), "/resources/perft/standard.epd"), depth_limit());
     }
 
     #[test]
-    #[ignore]
-    fn test_random() {
-        check_perft_file("G:/chess/epds/random.perft", 5);
+    fn chess960_perft_suite() {
+        check_perft_file(concat!(env!("CARGO_MANIFEST_DIR"), "/resources/perft/chess960.epd"), depth_limit());
     }
 }
@@ -0,0 +1,298 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::advanced::attack_info::AttackInfo;
+use crate::advanced::board::Board;
+use crate::advanced::move_list::MoveList;
+use crate::types::board_move::BoardMove;
+use crate::types::file::File;
+use crate::types::piece_type::PieceType;
+use crate::types::rank::Rank;
+use crate::types::square::Square;
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum SanError {
+    Empty,
+    InvalidSyntax(String),
+    IllegalMove(String),
+    AmbiguousMove(String),
+}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SanError::Empty => write!(formatter, "empty move"),
+            SanError::InvalidSyntax(san) => write!(formatter, "invalid move syntax '{}'", san),
+            SanError::IllegalMove(san) => write!(formatter, "illegal move '{}'", san),
+            SanError::AmbiguousMove(san) => write!(formatter, "ambiguous move '{}'", san),
+        }
+    }
+}
+
+impl Error for SanError {}
+
+impl Board {
+    const CASTLING_H_SIDE: &'static str = "O-O";
+    const CASTLING_A_SIDE: &'static str = "O-O-O";
+
+    // Expects a legal move
+    pub fn move_to_san(&self, board_move: &BoardMove, move_list: &mut MoveList, attack_info: &mut AttackInfo) -> String {
+        let square_from = board_move.square_from();
+        let square_to = board_move.square_to();
+        let move_type = board_move.move_type();
+        let piece_type = self.piece_type(&square_from);
+        let mut result = String::with_capacity(8);
+
+        if move_type.is_castling() {
+            if square_to.0 > square_from.0 {
+                result.push_str(Board::CASTLING_H_SIDE);
+            } else {
+                result.push_str(Board::CASTLING_A_SIDE);
+            }
+        } else {
+            let capture = move_type.is_passant() || self.piece_type(&square_to) != PieceType::NONE;
+            if piece_type == PieceType::PAWN {
+                if capture {
+                    result.push(square_from.to_file().to_char());
+                }
+            } else {
+                result.push(piece_type.to_char().to_ascii_uppercase());
+                self.push_disambiguation(&mut result, board_move, &piece_type, move_list, attack_info);
+            }
+            if capture {
+                result.push('x');
+            }
+            result.push_str(&square_to.to_string());
+            if move_type.is_promotion() {
+                result.push('=');
+                result.push(move_type.promoted_piece_type().to_char().to_ascii_uppercase());
+            }
+        }
+
+        let mut board = self.clone_position();
+        board.do_move(board_move);
+        if board.check_bitboard.is_not_empty() {
+            result.push(if board.legal_moves(move_list, attack_info).is_empty() { '#' } else { '+' });
+        }
+        result
+    }
+
+    // File is preferred over rank, both are used only when neither is enough
+    fn push_disambiguation(&self, result: &mut String, board_move: &BoardMove, piece_type: &PieceType,
+                           move_list: &mut MoveList, attack_info: &mut AttackInfo) {
+        let square_from = board_move.square_from();
+        let others: Vec<Square> = self.legal_moves(move_list, attack_info).iter()
+            .filter(|other| other.square_to() == board_move.square_to() &&
+                other.square_from() != square_from &&
+                self.piece_type(&other.square_from()) == *piece_type)
+            .map(|other| other.square_from())
+            .collect();
+        if others.is_empty() {
+            return;
+        }
+        let same_file = others.iter().any(|square| square.to_file() == square_from.to_file());
+        let same_rank = others.iter().any(|square| square.to_rank() == square_from.to_rank());
+        if !same_file {
+            result.push(square_from.to_file().to_char());
+        } else if !same_rank {
+            result.push(square_from.to_rank().to_char());
+        } else {
+            result.push_str(&square_from.to_string());
+        }
+    }
+
+    // Accepts 0-0 castling, missing or extra capture markers, promotions without '=' and coordinate moves
+    pub fn parse_san(&self, san: &str, move_list: &mut MoveList, attack_info: &mut AttackInfo) -> Result<BoardMove, SanError> {
+        let trimmed = san.trim()
+            .trim_end_matches(&['+', '#', '!', '?'][..])
+            .trim_end_matches("e.p.")
+            .trim_end();
+        if trimmed.is_empty() {
+            return Err(SanError::Empty);
+        }
+
+        let legal_moves = self.legal_moves(move_list, attack_info);
+        let castling = trimmed.replace(&['0', 'o'][..], "O");
+        if castling == Board::CASTLING_H_SIDE || castling == Board::CASTLING_A_SIDE {
+            let h_side = castling == Board::CASTLING_H_SIDE;
+            return legal_moves.iter()
+                .copied()
+                .find(|board_move| board_move.move_type().is_castling() &&
+                    (board_move.square_to().0 > board_move.square_from().0) == h_side)
+                .ok_or_else(|| SanError::IllegalMove(san.to_string()));
+        }
+
+        let invalid = || SanError::InvalidSyntax(san.to_string());
+        let mut chars: Vec<char> = trimmed.chars()
+            .filter(|&c| c != 'x' && c != ':' && c != '-')
+            .collect();
+
+        let mut piece_type = None;
+        if let Some(&first) = chars.first() {
+            if "NBRQK".contains(first) {
+                piece_type = Some(PieceType::from_char(first).0);
+                chars.remove(0);
+            }
+        }
+
+        let mut promoted_piece_type = PieceType::NONE;
+        if let Some(&last) = chars.last() {
+            if "NBRQnbrq".contains(last) {
+                promoted_piece_type = PieceType::from_char(last).0;
+                chars.pop();
+                if chars.last() == Some(&'=') {
+                    chars.pop();
+                }
+            }
+        }
+
+        if chars.len() < 2 || chars.len() > 4 {
+            return Err(invalid());
+        }
+        let square_to = Square::from_string(&chars[chars.len() - 2..].iter().collect::<String>()).ok_or_else(invalid)?;
+        let mut file_from = None;
+        let mut rank_from = None;
+        for &c in chars[..chars.len() - 2].iter() {
+            if let Some(file) = File::from_char(c) {
+                file_from = Some(file);
+            } else if let Some(rank) = Rank::from_char(c) {
+                rank_from = Some(rank);
+            } else {
+                return Err(invalid());
+            }
+        }
+        // Coordinate moves name the origin square instead of the piece
+        let coordinate = piece_type.is_none() && file_from.is_some() && rank_from.is_some();
+        if piece_type.is_none() && !coordinate {
+            piece_type = Some(PieceType::PAWN);
+        }
+
+        let mut candidates = legal_moves.iter()
+            .copied()
+            .filter(|board_move| {
+                let square_from = board_move.square_from();
+                // Coordinate castling may give either the king destination or the rook
+                let target = if !board_move.move_type().is_castling() {
+                    board_move.square_to() == square_to
+                } else {
+                    coordinate && (board_move.square_to() == square_to || board_move.castling_king_to() == square_to)
+                };
+                target &&
+                    piece_type.is_none_or(|piece_type| self.piece_type(&square_from) == piece_type) &&
+                    file_from.is_none_or(|file| square_from.to_file() == file) &&
+                    rank_from.is_none_or(|rank| square_from.to_rank() == rank) &&
+                    board_move.move_type().promoted_piece_type() == promoted_piece_type
+            });
+        let result = candidates.next().ok_or_else(|| SanError::IllegalMove(san.to_string()))?;
+        if candidates.next().is_some() {
+            return Err(SanError::AmbiguousMove(san.to_string()));
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::move_type::MoveType;
+
+    fn to_san(board: &Board, board_move: &BoardMove) -> String {
+        board.move_to_san(board_move, &mut MoveList::new(), &mut AttackInfo::new())
+    }
+
+    fn parse(board: &Board, san: &str) -> Result<BoardMove, SanError> {
+        board.parse_san(san, &mut MoveList::new(), &mut AttackInfo::new())
+    }
+
+    fn san(fen: &str, square_from: Square, square_to: Square, move_type: MoveType) -> String {
+        to_san(&Board::from_fen(fen), &BoardMove::build_move(&square_from, &square_to, &move_type))
+    }
+
+    #[test]
+    fn move_to_san() {
+        let board = Board::default();
+        assert_eq!(to_san(&board, &BoardMove::build_normal(&Square::E2, &Square::E4)), "e4");
+        assert_eq!(to_san(&board, &BoardMove::build_normal(&Square::G1, &Square::F3)), "Nf3");
+
+        let kiwipete = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -";
+        assert_eq!(san(kiwipete, Square::E1, Square::H1, MoveType::CASTLING), "O-O");
+        assert_eq!(san(kiwipete, Square::E1, Square::A1, MoveType::CASTLING), "O-O-O");
+        assert_eq!(san(kiwipete, Square::D5, Square::E6, MoveType::NORMAL), "dxe6");
+        assert_eq!(san(kiwipete, Square::E5, Square::F7, MoveType::NORMAL), "Nxf7");
+        assert_eq!(san(kiwipete, Square::F3, Square::H3, MoveType::NORMAL), "Qxh3");
+        assert_eq!(san("4k3/8/8/3pP3/8/8/8/4K3 w - d6", Square::E5, Square::D6, MoveType::PASSANT), "exd6");
+    }
+
+    #[test]
+    fn promotions_and_checks() {
+        assert_eq!(san("4k3/1P6/8/8/8/8/8/4K3 w - -", Square::B7, Square::B8, MoveType::PROMOTION_QUEEN), "b8=Q+");
+        assert_eq!(san("r3k3/1P6/8/8/8/8/8/4K3 w - -", Square::B7, Square::A8, MoveType::PROMOTION_KNIGHT), "bxa8=N");
+        assert_eq!(san("6k1/5ppp/8/8/8/8/8/R5K1 w - -", Square::A1, Square::A8, MoveType::NORMAL), "Ra8#");
+    }
+
+    #[test]
+    fn disambiguation() {
+        let fen = "4k3/8/8/8/8/8/4K3/R6R w - -";
+        assert_eq!(san(fen, Square::A1, Square::D1, MoveType::NORMAL), "Rad1");
+        assert_eq!(san(fen, Square::H1, Square::F1, MoveType::NORMAL), "Rhf1");
+        let fen = "4k3/8/8/R7/8/8/8/R3K3 w - -";
+        assert_eq!(san(fen, Square::A1, Square::A3, MoveType::NORMAL), "R1a3");
+        let fen = "8/7k/8/8/1Q6/8/1Q1Q4/7K w - -";
+        assert_eq!(san(fen, Square::B2, Square::D4, MoveType::NORMAL), "Qb2d4");
+        assert_eq!(san(fen, Square::D2, Square::D4, MoveType::NORMAL), "Qdd4");
+        assert_eq!(san(fen, Square::B4, Square::D4, MoveType::NORMAL), "Q4d4");
+    }
+
+    #[test]
+    fn parse_san() {
+        let board = Board::default();
+        assert_eq!(parse(&board, "e4"), Ok(BoardMove::build_normal(&Square::E2, &Square::E4)));
+        assert_eq!(parse(&board, "Nf3"), Ok(BoardMove::build_normal(&Square::G1, &Square::F3)));
+        assert_eq!(parse(&board, "g1f3"), Ok(BoardMove::build_normal(&Square::G1, &Square::F3)));
+        assert_eq!(parse(&board, "Ng1-f3!?"), Ok(BoardMove::build_normal(&Square::G1, &Square::F3)));
+        assert_eq!(parse(&board, "e5"), Err(SanError::IllegalMove("e5".to_string())));
+        assert_eq!(parse(&board, "Zf3"), Err(SanError::InvalidSyntax("Zf3".to_string())));
+        assert_eq!(parse(&board, " "), Err(SanError::Empty));
+
+        let board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R b KQkq -");
+        assert_eq!(parse(&board, "0-0"), Ok(BoardMove::build_castling(&Square::E8, &Square::H8)));
+        assert_eq!(parse(&board, "O-O-O+"), Ok(BoardMove::build_castling(&Square::E8, &Square::A8)));
+        assert_eq!(parse(&board, "e8g8"), Ok(BoardMove::build_castling(&Square::E8, &Square::H8)));
+
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/1R3KR1 w GB - 0 1");
+        assert_eq!(parse(&board, "O-O"), Ok(BoardMove::build_castling(&Square::F1, &Square::G1)));
+        assert_eq!(parse(&board, "f1g1"), Ok(BoardMove::build_castling(&Square::F1, &Square::G1)));
+        assert_eq!(parse(&board, "f1b1"), Ok(BoardMove::build_castling(&Square::F1, &Square::B1)));
+        assert_eq!(to_san(&board, &BoardMove::build_castling(&Square::F1, &Square::B1)), "O-O-O");
+
+        let board = Board::from_fen("r6r/4k3/8/8/8/8/8/4K3 b - -");
+        assert_eq!(parse(&board, "Rd8"), Err(SanError::AmbiguousMove("Rd8".to_string())));
+        assert_eq!(parse(&board, "Rad8"), Ok(BoardMove::build_normal(&Square::A8, &Square::D8)));
+    }
+
+    #[test]
+    fn parse_san_variants() {
+        let board = Board::from_fen("r3k3/1P6/8/8/8/8/8/4K3 w - -");
+        let promotion = BoardMove::build_move(&Square::B7, &Square::B8, &MoveType::PROMOTION_QUEEN);
+        assert_eq!(parse(&board, "b8=Q"), Ok(promotion));
+        assert_eq!(parse(&board, "b8Q"), Ok(promotion));
+        assert_eq!(parse(&board, "b7b8q"), Ok(promotion));
+        assert_eq!(parse(&board, "ba8N"), Ok(BoardMove::build_move(&Square::B7, &Square::A8, &MoveType::PROMOTION_KNIGHT)));
+        assert_eq!(parse(&board, "bxa8=N"), parse(&board, "ba8N"));
+
+        let board = Board::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6");
+        assert_eq!(parse(&board, "exd6 e.p."), Ok(BoardMove::build_passant(&Square::E5, &Square::D6)));
+        assert_eq!(parse(&board, "ed6"), Ok(BoardMove::build_passant(&Square::E5, &Square::D6)));
+    }
+
+    #[test]
+    fn round_trip() {
+        let board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -");
+        let mut move_list = MoveList::new();
+        let mut attack_info = AttackInfo::new();
+        for board_move in board.legal_moves(&mut MoveList::new(), &mut AttackInfo::new()).iter() {
+            let san = board.move_to_san(board_move, &mut move_list, &mut attack_info);
+            assert_eq!(board.parse_san(&san, &mut move_list, &mut attack_info), Ok(*board_move));
+        }
+    }
+}
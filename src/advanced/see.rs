@@ -0,0 +1,321 @@
+use crate::advanced::board::Board;
+use crate::types::bitboard::Bitboard;
+use crate::types::board_move::BoardMove;
+use crate::types::color::Color;
+use crate::types::piece_type::PieceType;
+use crate::types::square::Square;
+
+pub const SEE_VALUE: [i32; PieceType::NUM_PIECE_TYPES] = [0, 100, 325, 325, 500, 1000, 20000];
+
+impl Board {
+    #[inline]
+    pub fn attackers_to(&self, square: &Square, occupied: &Bitboard) -> Bitboard {
+        square.pawn_attacks(&Color::White).intersect(&self.piece_bitboard(&Color::Black, &PieceType::PAWN))
+            .union(&square.pawn_attacks(&Color::Black).intersect(&self.piece_bitboard(&Color::White, &PieceType::PAWN)))
+            .union(&square.knight_moves().intersect(&self.piece_type_bitboard(&PieceType::KNIGHT)))
+            .union(&square.king_moves().intersect(&self.piece_type_bitboard(&PieceType::KING)))
+            .union(&square.bishop_moves(occupied).intersect(&self.diagonal_sliders()))
+            .union(&square.rook_moves(occupied).intersect(&self.straight_sliders()))
+    }
+
+    #[inline]
+    fn diagonal_sliders(&self) -> Bitboard {
+        self.bishop_like_pieces(&Color::White).union(&self.bishop_like_pieces(&Color::Black))
+    }
+
+    #[inline]
+    fn straight_sliders(&self) -> Bitboard {
+        self.rook_like_pieces(&Color::White).union(&self.rook_like_pieces(&Color::Black))
+    }
+
+    // Material balance of the capture sequence started by the move on its target square
+    pub fn see(&self, board_move: &BoardMove) -> i32 {
+        let move_type = board_move.move_type();
+        if move_type.is_castling() {
+            return 0;
+        }
+
+        let square_from = board_move.square_from();
+        let square_to = board_move.square_to();
+        let mut color = self.color_at(&square_from).unwrap_or(self.color_to_move);
+        let mut occupied = self.game_bitboard().difference(&Bitboard::from_square(&square_from));
+        let mut piece_type = self.piece_type(&square_from);
+
+        let mut gain = [0; 32];
+        gain[0] = if move_type.is_passant() {
+            occupied = occupied.difference(&Bitboard::from_square(&square_to.forward(&color.reverse())));
+            SEE_VALUE[PieceType::PAWN.to_usize()]
+        } else {
+            SEE_VALUE[self.piece_type(&square_to).to_usize()]
+        };
+        if move_type.is_promotion() {
+            piece_type = move_type.promoted_piece_type();
+            gain[0] += SEE_VALUE[piece_type.to_usize()] - SEE_VALUE[PieceType::PAWN.to_usize()];
+        }
+
+        let promotion_square = Bitboard::RANK_1.union(&Bitboard::RANK_8).has(&Bitboard::from_square(&square_to));
+        let mut attackers = self.attackers_to(&square_to, &occupied);
+        let mut depth = 0;
+
+        loop {
+            color = color.reverse();
+            attackers = attackers.intersect(&occupied);
+            let color_attackers = attackers.intersect(&self.color_bitboard(&color));
+            if color_attackers.is_empty() {
+                break;
+            }
+            let attacker_type = self.least_valuable_piece(&color_attackers);
+            // King can only capture if the square is no longer defended
+            if attacker_type == PieceType::KING && attackers.has(&self.color_bitboard(&color.reverse())) {
+                break;
+            }
+
+            depth += 1;
+            gain[depth] = SEE_VALUE[piece_type.to_usize()] - gain[depth - 1];
+            piece_type = attacker_type;
+            if piece_type == PieceType::PAWN && promotion_square {
+                piece_type = PieceType::QUEEN;
+                gain[depth] += SEE_VALUE[PieceType::QUEEN.to_usize()] - SEE_VALUE[PieceType::PAWN.to_usize()];
+            }
+            if depth == gain.len() - 1 {
+                break;
+            }
+
+            let attacker = color_attackers.intersect(&self.piece_type_bitboard(&attacker_type));
+            occupied = occupied.difference(&Bitboard::from_square(&attacker.to_square()));
+
+            // Reveal x-ray attackers behind the piece that just captured
+            if attacker_type == PieceType::PAWN || attacker_type == PieceType::BISHOP || attacker_type == PieceType::QUEEN {
+                attackers = attackers.union(&square_to.bishop_moves(&occupied).intersect(&self.diagonal_sliders()));
+            }
+            if attacker_type == PieceType::ROOK || attacker_type == PieceType::QUEEN {
+                attackers = attackers.union(&square_to.rook_moves(&occupied).intersect(&self.straight_sliders()));
+            }
+        }
+
+        while depth > 0 {
+            gain[depth - 1] = -gain[depth].max(-gain[depth - 1]);
+            depth -= 1;
+        }
+        gain[0]
+    }
+
+    // Checks if the exchange started by the move wins at least threshold
+    pub fn see_ge(&self, board_move: &BoardMove, threshold: i32) -> bool {
+        let move_type = board_move.move_type();
+        if move_type.is_castling() {
+            return threshold <= 0;
+        }
+
+        let square_from = board_move.square_from();
+        let square_to = board_move.square_to();
+        let mut color = self.color_at(&square_from).unwrap_or(self.color_to_move);
+        let mut occupied = self.game_bitboard().difference(&Bitboard::from_square(&square_from));
+        let mut piece_type = self.piece_type(&square_from);
+
+        // Material over the threshold for the moving side if the last capture stands
+        let mut balance = if move_type.is_passant() {
+            occupied = occupied.difference(&Bitboard::from_square(&square_to.forward(&color.reverse())));
+            SEE_VALUE[PieceType::PAWN.to_usize()]
+        } else {
+            SEE_VALUE[self.piece_type(&square_to).to_usize()]
+        } - threshold;
+        if move_type.is_promotion() {
+            piece_type = move_type.promoted_piece_type();
+            balance += SEE_VALUE[piece_type.to_usize()] - SEE_VALUE[PieceType::PAWN.to_usize()];
+        }
+        if balance < 0 {
+            return false;
+        }
+
+        let promotion_square = Bitboard::RANK_1.union(&Bitboard::RANK_8).has(&Bitboard::from_square(&square_to));
+        // Losing the moving piece for nothing still reaches the threshold
+        if !promotion_square && balance >= SEE_VALUE[piece_type.to_usize()] {
+            return true;
+        }
+
+        let mut attackers = self.attackers_to(&square_to, &occupied);
+        let mut moving_side = true;
+
+        loop {
+            color = color.reverse();
+            attackers = attackers.intersect(&occupied);
+            let color_attackers = attackers.intersect(&self.color_bitboard(&color));
+            if color_attackers.is_empty() {
+                break;
+            }
+            let attacker_type = self.least_valuable_piece(&color_attackers);
+            // King can only capture if the square is no longer defended
+            if attacker_type == PieceType::KING && attackers.has(&self.color_bitboard(&color.reverse())) {
+                break;
+            }
+
+            let mut gain = SEE_VALUE[piece_type.to_usize()];
+            piece_type = attacker_type;
+            if piece_type == PieceType::PAWN && promotion_square {
+                piece_type = PieceType::QUEEN;
+                gain += SEE_VALUE[PieceType::QUEEN.to_usize()] - SEE_VALUE[PieceType::PAWN.to_usize()];
+            }
+            moving_side = !moving_side;
+            if moving_side {
+                balance += gain;
+                // Capturing back did not reach the threshold, so the exchange is lost either way
+                if balance < 0 {
+                    return false;
+                }
+            } else {
+                balance -= gain;
+                if balance >= 0 {
+                    return true;
+                }
+            }
+
+            let attacker = color_attackers.intersect(&self.piece_type_bitboard(&attacker_type));
+            occupied = occupied.difference(&Bitboard::from_square(&attacker.to_square()));
+
+            // Reveal x-ray attackers behind the piece that just captured
+            if attacker_type == PieceType::PAWN || attacker_type == PieceType::BISHOP || attacker_type == PieceType::QUEEN {
+                attackers = attackers.union(&square_to.bishop_moves(&occupied).intersect(&self.diagonal_sliders()));
+            }
+            if attacker_type == PieceType::ROOK || attacker_type == PieceType::QUEEN {
+                attackers = attackers.union(&square_to.rook_moves(&occupied).intersect(&self.straight_sliders()));
+            }
+        }
+
+        balance >= 0
+    }
+
+    #[inline]
+    fn least_valuable_piece(&self, bitboard: &Bitboard) -> PieceType {
+        for piece_type in PieceType::PIECE_TYPES[1..].iter() {
+            if bitboard.has(&self.piece_type_bitboard(piece_type)) {
+                return *piece_type;
+            }
+        }
+        PieceType::NONE
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::move_type::MoveType;
+
+    fn see_ge(fen: &str, square_from: Square, square_to: Square, threshold: i32) -> bool {
+        let board = Board::from_fen(fen);
+        board.see_ge(&BoardMove::build_normal(&square_from, &square_to), threshold)
+    }
+
+    #[test]
+    fn attackers_to() {
+        let board = Board::from_fen("4k3/8/8/3p4/4P3/2N5/8/3RK3 w - -");
+        let attackers = board.attackers_to(&Square::D5, &board.game_bitboard());
+        assert_eq!(attackers, Bitboard::C3.union(&Bitboard::E4).union(&Bitboard::D1));
+        let attackers = board.attackers_to(&Square::D4, &board.game_bitboard());
+        assert_eq!(attackers, Bitboard::D1);
+    }
+
+    #[test]
+    fn undefended_capture() {
+        assert!(see_ge("4k3/8/8/3p4/8/8/8/3QK3 w - -", Square::D1, Square::D5, 100));
+        assert!(!see_ge("4k3/8/8/3p4/8/8/8/3QK3 w - -", Square::D1, Square::D5, 101));
+    }
+
+    #[test]
+    fn defended_capture() {
+        assert!(!see_ge("3rk3/8/8/3p4/8/8/8/3QK3 w - -", Square::D1, Square::D5, 0));
+        assert!(see_ge("3rk3/8/8/3p4/8/8/8/3QK3 w - -", Square::D1, Square::D5, -900));
+        assert!(see_ge("3rk3/8/4p3/3p4/8/8/8/3RK3 w - -", Square::D1, Square::D5, -400));
+    }
+
+    #[test]
+    fn x_ray_attackers() {
+        assert!(see_ge("4k3/3r4/8/3p4/8/8/3R4/3RK3 w - -", Square::D2, Square::D5, 0));
+        // Rook behind the defender recaptures after it
+        assert!(!see_ge("3rk3/3r4/8/3p4/8/8/3R4/3RK3 w - -", Square::D2, Square::D5, 0));
+    }
+
+    #[test]
+    fn king_recapture() {
+        // King can not recapture a defended piece
+        assert!(see_ge("8/8/8/3pk3/8/8/3R4/3RK3 w - -", Square::D2, Square::D5, 100));
+        assert!(!see_ge("8/8/8/3pk3/8/8/8/3RK3 w - -", Square::D1, Square::D5, 1));
+    }
+
+    #[test]
+    fn exchange_value() {
+        let board = Board::from_fen("3rk3/8/8/3p4/8/8/8/3QK3 w - -");
+        assert_eq!(board.see(&BoardMove::build_normal(&Square::D1, &Square::D5)), -900);
+        let board = Board::from_fen("3rk3/3r4/8/3p4/8/8/3R4/3RK3 w - -");
+        assert_eq!(board.see(&BoardMove::build_normal(&Square::D2, &Square::D5)), -400);
+        let board = Board::from_fen("4k3/3r4/8/3p4/8/8/3R4/3RK3 w - -");
+        assert_eq!(board.see(&BoardMove::build_normal(&Square::D2, &Square::D5)), 100);
+        // Recapturing with the pawn still loses it to the second defender
+        let board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -");
+        assert_eq!(board.see(&BoardMove::build_normal(&Square::F3, &Square::F5)), -1000);
+    }
+
+    #[test]
+    fn en_passant() {
+        let board = Board::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6");
+        assert_eq!(board.see(&BoardMove::build_passant(&Square::E5, &Square::D6)), 100);
+        let board = Board::from_fen("4k3/2p5/8/3pP3/8/8/8/4K3 w - d6");
+        assert_eq!(board.see(&BoardMove::build_passant(&Square::E5, &Square::D6)), 0);
+        // Removing the captured pawn opens the file for the rook
+        let board = Board::from_fen("3rk3/8/8/3pP3/8/8/8/3RK3 w - d6");
+        assert_eq!(board.see(&BoardMove::build_passant(&Square::E5, &Square::D6)), 100);
+    }
+
+    #[test]
+    fn promotions() {
+        let board = Board::from_fen("4k3/1P6/8/8/8/8/8/4K3 w - -");
+        assert_eq!(board.see(&BoardMove::build_move(&Square::B7, &Square::B8, &MoveType::PROMOTION_QUEEN)), 900);
+        let board = Board::from_fen("1r2k3/P7/8/8/8/8/8/4K3 w - -");
+        assert_eq!(board.see(&BoardMove::build_move(&Square::A7, &Square::B8, &MoveType::PROMOTION_QUEEN)), 1400);
+        let board = Board::from_fen("r3k3/1P6/8/8/8/8/8/4K3 w - -");
+        assert_eq!(board.see(&BoardMove::build_move(&Square::B7, &Square::B8, &MoveType::PROMOTION_QUEEN)), -100);
+        assert_eq!(board.see(&BoardMove::build_move(&Square::B7, &Square::B8, &MoveType::PROMOTION_KNIGHT)), -100);
+        // Recapturing pawns promote too
+        let board = Board::from_fen("r3k3/P7/8/8/8/8/8/4K3 b - -");
+        assert_eq!(board.see(&BoardMove::build_normal(&Square::A8, &Square::B8)), -1400);
+    }
+
+    #[test]
+    fn see_ge_matches_see() {
+        let positions = [
+            ("4k3/8/8/3p4/8/8/8/3QK3 w - -", BoardMove::build_normal(&Square::D1, &Square::D5)),
+            ("3rk3/8/8/3p4/8/8/8/3QK3 w - -", BoardMove::build_normal(&Square::D1, &Square::D5)),
+            ("3rk3/8/4p3/3p4/8/8/8/3RK3 w - -", BoardMove::build_normal(&Square::D1, &Square::D5)),
+            ("4k3/3r4/8/3p4/8/8/3R4/3RK3 w - -", BoardMove::build_normal(&Square::D2, &Square::D5)),
+            ("3rk3/3r4/8/3p4/8/8/3R4/3RK3 w - -", BoardMove::build_normal(&Square::D2, &Square::D5)),
+            ("8/8/8/3pk3/8/8/3R4/3RK3 w - -", BoardMove::build_normal(&Square::D2, &Square::D5)),
+            ("8/8/8/3pk3/8/8/8/3RK3 w - -", BoardMove::build_normal(&Square::D1, &Square::D5)),
+            ("4k3/8/8/3pP3/8/8/8/4K3 w - d6", BoardMove::build_passant(&Square::E5, &Square::D6)),
+            ("4k3/2p5/8/3pP3/8/8/8/4K3 w - d6", BoardMove::build_passant(&Square::E5, &Square::D6)),
+            ("3rk3/8/8/3pP3/8/8/8/3RK3 w - d6", BoardMove::build_passant(&Square::E5, &Square::D6)),
+            ("4k3/1P6/8/8/8/8/8/4K3 w - -", BoardMove::build_move(&Square::B7, &Square::B8, &MoveType::PROMOTION_QUEEN)),
+            ("1r2k3/P7/8/8/8/8/8/4K3 w - -", BoardMove::build_move(&Square::A7, &Square::B8, &MoveType::PROMOTION_QUEEN)),
+            ("r3k3/1P6/8/8/8/8/8/4K3 w - -", BoardMove::build_move(&Square::B7, &Square::B8, &MoveType::PROMOTION_QUEEN)),
+            ("r3k3/1P6/8/8/8/8/8/4K3 w - -", BoardMove::build_move(&Square::B7, &Square::B8, &MoveType::PROMOTION_KNIGHT)),
+            ("r3k3/P7/8/8/8/8/8/4K3 b - -", BoardMove::build_normal(&Square::A8, &Square::B8)),
+            ("4k3/8/8/8/8/8/8/3QK3 w - -", BoardMove::build_normal(&Square::D1, &Square::D4)),
+            ("4k3/4p3/8/8/8/8/8/3QK3 w - -", BoardMove::build_normal(&Square::D1, &Square::D6)),
+            ("r3k2r/8/8/8/8/8/8/R3K2R w KQkq -", BoardMove::build_move(&Square::E1, &Square::H1, &MoveType::CASTLING)),
+        ];
+        for (fen, board_move) in positions.iter() {
+            let board = Board::from_fen(fen);
+            let see = board.see(board_move);
+            for threshold in (-2000..=2000).step_by(25).chain(see - 1..=see + 1) {
+                assert_eq!(board.see_ge(board_move, threshold), see >= threshold, "{} {}", fen, threshold);
+            }
+        }
+    }
+
+    #[test]
+    fn quiet_moves() {
+        assert!(see_ge("4k3/8/8/8/8/8/8/3QK3 w - -", Square::D1, Square::D4, 0));
+        assert!(!see_ge("4k3/4p3/8/8/8/8/8/3QK3 w - -", Square::D1, Square::D6, 0));
+        let board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq -");
+        assert!(board.see_ge(&BoardMove::build_move(&Square::E1, &Square::H1, &MoveType::CASTLING), 0));
+    }
+}
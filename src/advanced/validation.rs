@@ -0,0 +1,192 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::advanced::board::Board;
+use crate::types::bitboard::Bitboard;
+use crate::types::castling_rights::CastlingIndex;
+use crate::types::color::Color;
+use crate::types::piece_type::PieceType;
+use crate::types::rank::Rank;
+use crate::types::square::Square;
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum PositionError {
+    KingCount(Color, u32),
+    TooManyPawns(Color, u32),
+    PawnOnBackRank(Square),
+    OpponentInCheck,
+    TooManyCheckers(u32),
+    CastlingWithoutKing(CastlingIndex),
+    CastlingWithoutRook(CastlingIndex),
+    InvalidEnPassant(Square),
+}
+
+impl fmt::Display for PositionError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PositionError::KingCount(color, count) => write!(formatter, "{} has {} kings", color.to_char(), count),
+            PositionError::TooManyPawns(color, count) => write!(formatter, "{} has {} pawns", color.to_char(), count),
+            PositionError::PawnOnBackRank(square) => write!(formatter, "pawn on {}", square.to_string()),
+            PositionError::OpponentInCheck => write!(formatter, "side not to move is in check"),
+            PositionError::TooManyCheckers(count) => write!(formatter, "king attacked by {} pieces", count),
+            PositionError::CastlingWithoutKing(index) => write!(formatter, "castling right {} without king", index.to_char()),
+            PositionError::CastlingWithoutRook(index) => write!(formatter, "castling right {} without rook", index.to_char()),
+            PositionError::InvalidEnPassant(square) => write!(formatter, "no pawn to capture en passant on {}", square.to_string()),
+        }
+    }
+}
+
+impl Error for PositionError {}
+
+impl Board {
+    pub const MAX_PAWNS: u32 = 8;
+
+    // Checks the position could be reached from a legal game, as far as it can be told cheaply
+    pub fn validate(&self) -> Result<(), PositionError> {
+        for color in Color::COLORS.iter() {
+            let kings = self.piece_bitboard(color, &PieceType::KING).count();
+            if kings != 1 {
+                return Err(PositionError::KingCount(*color, kings));
+            }
+            let pawns = self.piece_bitboard(color, &PieceType::PAWN).count();
+            if pawns > Board::MAX_PAWNS {
+                return Err(PositionError::TooManyPawns(*color, pawns));
+            }
+        }
+
+        let back_rank_pawns = self.piece_type_bitboard(&PieceType::PAWN)
+            .intersect(&Bitboard::RANK_1.union(&Bitboard::RANK_8));
+        if back_rank_pawns.is_not_empty() {
+            return Err(PositionError::PawnOnBackRank(back_rank_pawns.to_square()));
+        }
+
+        let our_color = self.color_to_move;
+        let their_color = our_color.reverse();
+        let occupied = self.game_bitboard();
+        if self.attackers_to(&self.placed_king_square(&their_color), &occupied).has(&self.color_bitboard(&our_color)) {
+            return Err(PositionError::OpponentInCheck);
+        }
+        let checkers = self.attackers_to(&self.placed_king_square(&our_color), &occupied)
+            .intersect(&self.color_bitboard(&their_color))
+            .count();
+        if checkers > 2 {
+            return Err(PositionError::TooManyCheckers(checkers));
+        }
+
+        self.validate_castling_rights()?;
+        self.validate_ep_square()
+    }
+
+    // Boards built with add_piece do not keep the cached king square up to date
+    #[inline]
+    fn placed_king_square(&self, color: &Color) -> Square {
+        self.piece_bitboard(color, &PieceType::KING).to_square()
+    }
+
+    fn validate_castling_rights(&self) -> Result<(), PositionError> {
+        for castling_index in self.castling_rights.iterator() {
+            let color = castling_index.color();
+            let king_square = self.placed_king_square(&color);
+            if king_square.to_rank() != Rank::RANK_1.relative(&color) {
+                return Err(PositionError::CastlingWithoutKing(castling_index));
+            }
+            // The rook has to be on the castling side of the king
+            let rook_square = self.initial_rook_square(&castling_index);
+            if !self.piece_bitboard(&color, &PieceType::ROOK).has(&Bitboard::from_square(&rook_square)) ||
+                rook_square.to_rank() != king_square.to_rank() ||
+                Board::castling_index(&color, &king_square, &rook_square) != castling_index {
+                return Err(PositionError::CastlingWithoutRook(castling_index));
+            }
+        }
+        Ok(())
+    }
+
+    // The pawn that just moved two squares must be in front of the ep square with both squares it crossed empty
+    fn validate_ep_square(&self) -> Result<(), PositionError> {
+        if let Some(ep_square) = self.ep_square {
+            let our_color = self.color_to_move;
+            let their_color = our_color.reverse();
+            let crossed = Bitboard::from_square(&ep_square)
+                .union(&Bitboard::from_square(&ep_square.forward(&our_color)));
+            if ep_square.to_rank() != Rank::RANK_6.relative(&our_color) ||
+                !self.piece_bitboard(&their_color, &PieceType::PAWN).has(&Bitboard::from_square(&ep_square.forward(&their_color))) ||
+                self.game_bitboard().has(&crossed) {
+                return Err(PositionError::InvalidEnPassant(ep_square));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::board_move::BoardMove;
+
+    #[test]
+    fn valid_positions() {
+        assert_eq!(Board::default().validate(), Ok(()));
+        assert_eq!(Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -").validate(), Ok(()));
+        assert_eq!(Board::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1").validate(), Ok(()));
+    }
+
+    #[test]
+    fn king_count() {
+        let mut board = Board::default();
+        board.add_piece(&Color::White, &PieceType::KING, &Square::E4);
+        board.initial_pass();
+        assert_eq!(board.validate(), Err(PositionError::KingCount(Color::White, 2)));
+    }
+
+    #[test]
+    fn pawns() {
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/P3K3 w - -");
+        assert_eq!(board.validate(), Err(PositionError::PawnOnBackRank(Square::A1)));
+        let board = Board::from_fen("4k3/8/8/8/8/P7/PPPPPPPP/4K3 w - -");
+        assert_eq!(board.validate(), Err(PositionError::TooManyPawns(Color::White, 9)));
+    }
+
+    #[test]
+    fn checks() {
+        let board = Board::from_fen("4k3/8/8/8/8/8/4R3/4K3 b - -");
+        assert_eq!(board.validate(), Ok(()));
+        let board = Board::from_fen("4k3/8/8/8/8/8/4R3/4K3 w - -");
+        assert_eq!(board.validate(), Err(PositionError::OpponentInCheck));
+        let board = Board::from_fen("4k3/8/8/8/8/3n1n2/4r3/4K3 w - -");
+        assert_eq!(board.validate(), Err(PositionError::TooManyCheckers(3)));
+
+        let mut board = Board::empty();
+        board.add_piece(&Color::White, &PieceType::KING, &Square::B2);
+        board.add_piece(&Color::Black, &PieceType::KING, &Square::G7);
+        board.add_piece(&Color::White, &PieceType::ROOK, &Square::G2);
+        board.initial_pass();
+        assert_eq!(board.validate(), Err(PositionError::OpponentInCheck));
+        board.color_to_move = Color::Black;
+        board.initial_pass();
+        assert_eq!(board.validate(), Ok(()));
+    }
+
+    #[test]
+    fn castling_rights() {
+        let mut board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq -");
+        assert_eq!(board.validate(), Ok(()));
+        board.do_move(&BoardMove::build_normal(&Square::H1, &Square::H2));
+        board.castling_rights = board.castling_rights.add_index(&CastlingIndex::WhiteH);
+        assert_eq!(board.validate(), Err(PositionError::CastlingWithoutRook(CastlingIndex::WhiteH)));
+
+        let mut board = Board::from_fen("1r2k1r1/8/8/8/8/8/8/1R2K1R1 w KQkq -");
+        assert_eq!(board.validate(), Ok(()));
+        board.do_move(&BoardMove::build_normal(&Square::E1, &Square::E2));
+        board.castling_rights = board.castling_rights.add_index(&CastlingIndex::WhiteA);
+        assert_eq!(board.validate(), Err(PositionError::CastlingWithoutKing(CastlingIndex::WhiteA)));
+    }
+
+    #[test]
+    fn en_passant() {
+        let mut board = Board::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+        board.ep_square = Some(Square::D3);
+        assert_eq!(board.validate(), Err(PositionError::InvalidEnPassant(Square::D3)));
+        board.ep_square = Some(Square::E6);
+        assert_eq!(board.validate(), Err(PositionError::InvalidEnPassant(Square::E6)));
+    }
+}
@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+use std::io;
+use std::io::Write;
+
+use crate::book::polyglot_book::PolyglotEntry;
+use crate::pgn::pgn_game::{PgnError, PgnGame, PgnResult};
+use crate::types::color::Color;
+
+// Results of the games where the move was played, from the point of view of its side
+#[derive(PartialEq, Eq, Copy, Clone, Debug, Default)]
+pub struct MoveStats {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl MoveStats {
+    #[inline]
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    // Half a point per draw, between 0 and 1
+    #[inline]
+    pub fn score(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.0;
+        }
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games() as f64
+    }
+
+    // Polyglot convention, two points per win and one per draw
+    #[inline]
+    pub fn weight(&self) -> u64 {
+        2 * self.wins as u64 + self.draws as u64
+    }
+}
+
+pub struct BookBuilder {
+    max_ply: usize,
+    min_games: u32,
+    min_score: f64,
+    // Keyed by position and encoded move
+    stats: HashMap<(u64, u16), MoveStats>,
+    games: usize,
+}
+
+impl BookBuilder {
+    pub const DEFAULT_MAX_PLY: usize = 24;
+
+    pub fn new(max_ply: usize) -> Self {
+        BookBuilder {
+            max_ply,
+            min_games: 1,
+            min_score: 0.0,
+            stats: HashMap::new(),
+            games: 0,
+        }
+    }
+
+    pub fn set_min_games(&mut self, min_games: u32) {
+        self.min_games = min_games;
+    }
+
+    // Fraction of the points, 0.5 keeps moves that did at least as well as a draw
+    pub fn set_min_score(&mut self, min_score: f64) {
+        self.min_score = min_score;
+    }
+
+    #[inline]
+    pub fn games(&self) -> usize {
+        self.games
+    }
+
+    #[inline]
+    pub fn stats(&self, key: u64, raw_move: u16) -> Option<MoveStats> {
+        self.stats.get(&(key, raw_move)).copied()
+    }
+
+    // Games without a result say nothing about the moves and are left out
+    pub fn add_game(&mut self, game: &PgnGame) -> Result<bool, PgnError> {
+        let winner = match game.result {
+            PgnResult::WhiteWins => Some(Color::White),
+            PgnResult::BlackWins => Some(Color::Black),
+            PgnResult::Draw => None,
+            PgnResult::Unknown => return Ok(false),
+        };
+        let mut board = game.starting_board()?;
+        for pgn_move in game.moves.iter().take(self.max_ply) {
+            let entry = self.stats
+                .entry((board.polyglot_key(), PolyglotEntry::encode_move(&pgn_move.board_move)))
+                .or_default();
+            match winner {
+                Some(color) if color == board.color_to_move => entry.wins += 1,
+                Some(_) => entry.losses += 1,
+                None => entry.draws += 1,
+            }
+            board.do_move(&pgn_move.board_move);
+        }
+        self.games += 1;
+        Ok(true)
+    }
+
+    // Sorted by key and then by weight, weights are scaled down when a position overflows them
+    pub fn entries(&self) -> Vec<PolyglotEntry> {
+        let mut selected: Vec<(u64, u16, u64)> = self.stats.iter()
+            .filter(|(_, stats)| stats.games() >= self.min_games && stats.score() >= self.min_score)
+            .map(|((key, raw_move), stats)| (*key, *raw_move, stats.weight()))
+            .collect();
+        selected.sort_unstable_by(|first, second| first.0.cmp(&second.0)
+            .then(second.2.cmp(&first.2))
+            .then(first.1.cmp(&second.1)));
+
+        let mut result = Vec::with_capacity(selected.len());
+        let mut index = 0;
+        while index < selected.len() {
+            let key = selected[index].0;
+            // The first entry of each position has the highest weight
+            let max_weight = selected[index].2.max(u16::MAX as u64);
+            while index < selected.len() && selected[index].0 == key {
+                let (_, raw_move, weight) = selected[index];
+                result.push(PolyglotEntry {
+                    key,
+                    raw_move,
+                    weight: (weight * u16::MAX as u64 / max_weight) as u16,
+                    learn: 0,
+                });
+                index += 1;
+            }
+        }
+        result
+    }
+
+    // Returns the number of entries written
+    pub fn write<W: Write>(&self, output: &mut W) -> io::Result<usize> {
+        let entries = self.entries();
+        for entry in entries.iter() {
+            output.write_all(&entry.to_bytes())?;
+        }
+        output.flush()?;
+        Ok(entries.len())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::advanced::board::Board;
+    use crate::book::polyglot_book::PolyglotBook;
+    use crate::pgn::pgn_reader::PgnReader;
+    use crate::types::board_move::BoardMove;
+    use crate::types::square::Square;
+
+    const GAMES: &str = "[Result \"1-0\"]\n\n1. e4 e5 2. Nf3 1-0\n\n\
+        [Result \"1/2-1/2\"]\n\n1. e4 c5 1/2-1/2\n\n\
+        [Result \"0-1\"]\n\n1. d4 d5 0-1\n\n\
+        [Result \"*\"]\n\n1. c4 *\n\n\
+        [Result \"1-0\"]\n\n1. e4 e5 2. Bc4 1-0\n\n";
+
+    fn builder(max_ply: usize) -> BookBuilder {
+        let mut result = BookBuilder::new(max_ply);
+        for game in PgnReader::new(GAMES.as_bytes()) {
+            result.add_game(&game.unwrap()).unwrap();
+        }
+        result
+    }
+
+    #[test]
+    fn statistics() {
+        let builder = builder(2);
+        assert_eq!(builder.games(), 4);
+        let start_key = Board::default().polyglot_key();
+        let e4 = PolyglotEntry::encode_move(&BoardMove::build_normal(&Square::E2, &Square::E4));
+        assert_eq!(builder.stats(start_key, e4), Some(MoveStats { wins: 2, draws: 1, losses: 0 }));
+        let d4 = PolyglotEntry::encode_move(&BoardMove::build_normal(&Square::D2, &Square::D4));
+        assert_eq!(builder.stats(start_key, d4), Some(MoveStats { wins: 0, draws: 0, losses: 1 }));
+        let c4 = PolyglotEntry::encode_move(&BoardMove::build_normal(&Square::C2, &Square::C4));
+        assert_eq!(builder.stats(start_key, c4), None);
+
+        let mut board = Board::default();
+        board.do_move(&BoardMove::build_normal(&Square::E2, &Square::E4));
+        let e5 = PolyglotEntry::encode_move(&BoardMove::build_normal(&Square::E7, &Square::E5));
+        assert_eq!(builder.stats(board.polyglot_key(), e5), Some(MoveStats { wins: 0, draws: 0, losses: 2 }));
+        // Beyond the ply limit
+        assert_eq!(builder.entries().len(), 5);
+    }
+
+    #[test]
+    fn filters() {
+        let mut builder = builder(4);
+        builder.set_min_games(2);
+        assert_eq!(builder.entries().len(), 2);
+        builder.set_min_score(0.5);
+        let entries = builder.entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].key, Board::default().polyglot_key());
+        assert_eq!(entries[0].weight, 5);
+    }
+
+    #[test]
+    fn write_book() {
+        let builder = builder(4);
+        let mut bytes = Vec::new();
+        assert_eq!(builder.write(&mut bytes).unwrap(), 7);
+        let book = PolyglotBook::read(bytes.as_slice()).unwrap();
+        assert_eq!(book.len(), 7);
+
+        let board = Board::default();
+        assert_eq!(book.moves(&board), vec![
+            (BoardMove::build_normal(&Square::E2, &Square::E4), 5),
+            (BoardMove::build_normal(&Square::D2, &Square::D4), 0),
+        ]);
+        assert_eq!(book.best_move(&board), Some(BoardMove::build_normal(&Square::E2, &Square::E4)));
+    }
+}
@@ -0,0 +1,4 @@
+pub mod book_builder;
+pub mod polyglot_book;
+pub mod polyglot_key;
+pub mod polyglot_random;
@@ -0,0 +1,251 @@
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, Read};
+
+use crate::advanced::attack_info::AttackInfo;
+use crate::advanced::board::Board;
+use crate::advanced::move_list::MoveList;
+use crate::types::board_move::BoardMove;
+use crate::types::piece_type::PieceType;
+use crate::types::square::Square;
+use crate::utils::random::Random;
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub struct PolyglotEntry {
+    pub key: u64,
+    pub raw_move: u16,
+    pub weight: u16,
+    pub learn: u32,
+}
+
+impl PolyglotEntry {
+    // Big endian key, move, weight and learn fields
+    pub const SIZE: usize = 16;
+
+    const SQUARE_MASK: u16 = 0x3F;
+    const FROM_SHIFT: u16 = 6;
+    const PROMOTION_SHIFT: u16 = 12;
+    const PROMOTION_MASK: u16 = 0x7;
+
+    pub fn from_bytes(bytes: &[u8; PolyglotEntry::SIZE]) -> Self {
+        let mut key = [0; 8];
+        key.copy_from_slice(&bytes[0..8]);
+        PolyglotEntry {
+            key: u64::from_be_bytes(key),
+            raw_move: u16::from_be_bytes([bytes[8], bytes[9]]),
+            weight: u16::from_be_bytes([bytes[10], bytes[11]]),
+            learn: u32::from_be_bytes([bytes[12], bytes[13], bytes[14], bytes[15]]),
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; PolyglotEntry::SIZE] {
+        let mut result = [0; PolyglotEntry::SIZE];
+        result[0..8].copy_from_slice(&self.key.to_be_bytes());
+        result[8..10].copy_from_slice(&self.raw_move.to_be_bytes());
+        result[10..12].copy_from_slice(&self.weight.to_be_bytes());
+        result[12..16].copy_from_slice(&self.learn.to_be_bytes());
+        result
+    }
+
+    pub fn encode_move(board_move: &BoardMove) -> u16 {
+        let promoted_piece_type = board_move.move_type().promoted_piece_type();
+        let promotion = if promoted_piece_type == PieceType::NONE {
+            0
+        } else {
+            (promoted_piece_type.0 - PieceType::PAWN.0) as u16
+        };
+        board_move.square_to().to_u16() |
+            board_move.square_from().to_u16() << PolyglotEntry::FROM_SHIFT |
+            promotion << PolyglotEntry::PROMOTION_SHIFT
+    }
+
+    // Castling is stored as the king taking its rook, just like our moves
+    pub fn decode_move(&self, legal_moves: &[BoardMove]) -> Option<BoardMove> {
+        let square_to = Square((self.raw_move & PolyglotEntry::SQUARE_MASK) as i8);
+        let square_from = Square((self.raw_move >> PolyglotEntry::FROM_SHIFT & PolyglotEntry::SQUARE_MASK) as i8);
+        let promotion = self.raw_move >> PolyglotEntry::PROMOTION_SHIFT & PolyglotEntry::PROMOTION_MASK;
+        let promoted_piece_type = if promotion == 0 {
+            PieceType::NONE
+        } else {
+            PieceType(PieceType::PAWN.0 + promotion as u8)
+        };
+        legal_moves.iter()
+            .copied()
+            .find(|board_move| board_move.square_from() == square_from &&
+                board_move.square_to() == square_to &&
+                board_move.move_type().promoted_piece_type() == promoted_piece_type)
+    }
+}
+
+// Entries are sorted by key, positions may have several entries next to each other
+pub struct PolyglotBook {
+    entries: Vec<PolyglotEntry>,
+}
+
+impl PolyglotBook {
+    pub fn open(path: &str) -> io::Result<Self> {
+        PolyglotBook::read(BufReader::new(File::open(path)?))
+    }
+
+    pub fn read<R: Read>(mut input: R) -> io::Result<Self> {
+        let mut bytes = Vec::new();
+        input.read_to_end(&mut bytes)?;
+        if bytes.len() % PolyglotEntry::SIZE != 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "truncated polyglot entry"));
+        }
+        let entries = bytes.chunks_exact(PolyglotEntry::SIZE)
+            .map(|chunk| {
+                let mut entry = [0; PolyglotEntry::SIZE];
+                entry.copy_from_slice(chunk);
+                PolyglotEntry::from_bytes(&entry)
+            })
+            .collect();
+        Ok(PolyglotBook { entries })
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn entries(&self, key: u64) -> &[PolyglotEntry] {
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        let end = start + self.entries[start..].partition_point(|entry| entry.key == key);
+        &self.entries[start..end]
+    }
+
+    // Entries with moves that are not legal in the position are skipped
+    pub fn moves(&self, board: &Board) -> Vec<(BoardMove, u16)> {
+        let entries = self.entries(board.polyglot_key());
+        if entries.is_empty() {
+            return Vec::new();
+        }
+        let legal_moves = board.legal_moves(&mut MoveList::new(), &mut AttackInfo::new()).to_vec();
+        entries.iter()
+            .filter_map(|entry| entry.decode_move(&legal_moves).map(|board_move| (board_move, entry.weight)))
+            .collect()
+    }
+
+    // Moves with no weight are kept for statistics but never played
+    pub fn best_move(&self, board: &Board) -> Option<BoardMove> {
+        self.moves(board).into_iter()
+            .filter(|(_, weight)| *weight > 0)
+            .fold(None, |best: Option<(BoardMove, u16)>, (board_move, weight)| match best {
+                Some((_, best_weight)) if best_weight >= weight => best,
+                _ => Some((board_move, weight)),
+            })
+            .map(|(board_move, _)| board_move)
+    }
+
+    // Each move is picked with a chance proportional to its weight
+    pub fn weighted_move(&self, board: &Board, random: &mut Random) -> Option<BoardMove> {
+        let moves = self.moves(board);
+        let total: u64 = moves.iter().map(|(_, weight)| *weight as u64).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut pick = random.next() % total;
+        for (board_move, weight) in moves.into_iter() {
+            if pick < weight as u64 {
+                return Some(board_move);
+            }
+            pick -= weight as u64;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::move_type::MoveType;
+
+    fn entry_bytes(key: u64, from: Square, to: Square, promotion: u16, weight: u16) -> Vec<u8> {
+        let raw_move = to.to_u16() | from.to_u16() << 6 | promotion << 12;
+        let mut result = Vec::new();
+        result.extend_from_slice(&key.to_be_bytes());
+        result.extend_from_slice(&raw_move.to_be_bytes());
+        result.extend_from_slice(&weight.to_be_bytes());
+        result.extend_from_slice(&0u32.to_be_bytes());
+        result
+    }
+
+    fn start_book() -> PolyglotBook {
+        let key = Board::default().polyglot_key();
+        let mut bytes = Vec::new();
+        bytes.extend(entry_bytes(key - 1, Square::A2, Square::A3, 0, 1));
+        bytes.extend(entry_bytes(key, Square::E2, Square::E4, 0, 30));
+        bytes.extend(entry_bytes(key, Square::D2, Square::D4, 0, 10));
+        bytes.extend(entry_bytes(key, Square::E2, Square::E5, 0, 50));
+        bytes.extend(entry_bytes(key, Square::A2, Square::A4, 0, 0));
+        bytes.extend(entry_bytes(key + 1, Square::H2, Square::H3, 0, 1));
+        PolyglotBook::read(bytes.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn read_entries() {
+        let book = start_book();
+        assert_eq!(book.len(), 6);
+        let entries = book.entries(Board::default().polyglot_key());
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[0].weight, 30);
+        assert!(book.entries(0).is_empty());
+        assert!(PolyglotBook::read(&[0u8; 17][..]).is_err());
+    }
+
+    #[test]
+    fn decode_moves() {
+        let board = Board::default();
+        let book = start_book();
+        assert_eq!(book.moves(&board), vec![
+            (BoardMove::build_normal(&Square::E2, &Square::E4), 30),
+            (BoardMove::build_normal(&Square::D2, &Square::D4), 10),
+            (BoardMove::build_normal(&Square::A2, &Square::A4), 0),
+        ]);
+
+        let board = Board::from_fen("r3k2r/1P6/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        let mut move_list = MoveList::new();
+        let legal_moves = board.legal_moves(&mut move_list, &mut AttackInfo::new());
+        let entry = |from: Square, to: Square, promotion: u16| {
+            let raw_move = to.to_u16() | from.to_u16() << 6 | promotion << 12;
+            PolyglotEntry { key: board.polyglot_key(), raw_move, weight: 1, learn: 0 }.decode_move(legal_moves)
+        };
+        assert_eq!(entry(Square::E1, Square::H1, 0), Some(BoardMove::build_castling(&Square::E1, &Square::H1)));
+        assert_eq!(entry(Square::E1, Square::A1, 0), Some(BoardMove::build_castling(&Square::E1, &Square::A1)));
+        assert_eq!(entry(Square::B7, Square::A8, 4),
+                   Some(BoardMove::build_move(&Square::B7, &Square::A8, &MoveType::PROMOTION_QUEEN)));
+        assert_eq!(entry(Square::B7, Square::B8, 1),
+                   Some(BoardMove::build_move(&Square::B7, &Square::B8, &MoveType::PROMOTION_KNIGHT)));
+        assert_eq!(entry(Square::B7, Square::B8, 0), None);
+
+        let promotion = BoardMove::build_move(&Square::B7, &Square::A8, &MoveType::PROMOTION_ROOK);
+        let entry = PolyglotEntry { key: 1, raw_move: PolyglotEntry::encode_move(&promotion), weight: 2, learn: 3 };
+        assert_eq!(entry.decode_move(legal_moves), Some(promotion));
+        assert_eq!(PolyglotEntry::from_bytes(&entry.to_bytes()), entry);
+    }
+
+    #[test]
+    fn pick_moves() {
+        let board = Board::default();
+        let book = start_book();
+        let e4 = BoardMove::build_normal(&Square::E2, &Square::E4);
+        let d4 = BoardMove::build_normal(&Square::D2, &Square::D4);
+        assert_eq!(book.best_move(&board), Some(e4));
+
+        let mut random = Random::new();
+        let picks: Vec<BoardMove> = (0..400).map(|_| book.weighted_move(&board, &mut random).unwrap()).collect();
+        let e4_count = picks.iter().filter(|board_move| **board_move == e4).count();
+        let d4_count = picks.iter().filter(|board_move| **board_move == d4).count();
+        assert_eq!(e4_count + d4_count, picks.len());
+        assert!(e4_count > 2 * d4_count && d4_count > 0);
+
+        let board = Board::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+        assert_eq!(book.best_move(&board), None);
+        assert_eq!(book.weighted_move(&board, &mut random), None);
+    }
+}
@@ -0,0 +1,82 @@
+use crate::advanced::board::Board;
+use crate::book::polyglot_random::POLYGLOT_RANDOM;
+use crate::types::color::Color;
+use crate::types::piece_type::PieceType;
+use crate::types::square::Square;
+
+impl Board {
+    const POLYGLOT_CASTLING_OFFSET: usize = 768;
+    const POLYGLOT_EP_OFFSET: usize = 772;
+    const POLYGLOT_TURN_OFFSET: usize = 780;
+
+    // Polyglot pieces go black pawn, white pawn, black knight and so on up to the white king
+    #[inline]
+    fn polyglot_piece(color: &Color, piece_type: &PieceType, square: &Square) -> u64 {
+        let kind = 2 * (piece_type.to_usize() - PieceType::PAWN.to_usize()) + if color.is_white() { 1 } else { 0 };
+        POLYGLOT_RANDOM[Square::NUM_SQUARES * kind + square.to_usize()]
+    }
+
+    // Separate from the engine keys, en passant only counts when a pawn can take
+    pub fn polyglot_key(&self) -> u64 {
+        let mut result = 0;
+        for color in Color::COLORS.iter() {
+            for piece_type in PieceType::PIECE_TYPES.iter().skip(1) {
+                for square in self.piece_bitboard(color, piece_type).iterator() {
+                    result ^= Board::polyglot_piece(color, piece_type, &square);
+                }
+            }
+        }
+        for castling_index in self.castling_rights.iterator() {
+            result ^= POLYGLOT_RANDOM[Board::POLYGLOT_CASTLING_OFFSET + castling_index.to_usize()];
+        }
+        if let Some(ep_square) = self.ep_square {
+            let our_color = self.color_to_move;
+            if ep_square.pawn_attacks(&our_color.reverse()).intersect(&self.piece_bitboard(&our_color, &PieceType::PAWN)).is_not_empty() {
+                result ^= POLYGLOT_RANDOM[Board::POLYGLOT_EP_OFFSET + ep_square.to_file().to_usize()];
+            }
+        }
+        if self.color_to_move.is_white() {
+            result ^= POLYGLOT_RANDOM[Board::POLYGLOT_TURN_OFFSET];
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::advanced::attack_info::AttackInfo;
+    use crate::advanced::move_list::MoveList;
+
+    // Reference keys published with the book format
+    #[test]
+    fn reference_keys() {
+        let cases = [
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", 0x463b96181691fc9c),
+            ("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1", 0x823c9b50fd114196),
+            ("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 2", 0x0756b94461c50fb0),
+            ("rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR b KQkq - 0 2", 0x662fafb965db29d4),
+            ("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3", 0x22a48b5a8e47ff78),
+            ("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR b kq - 0 3", 0x652a607ca3f242c1),
+            ("rnbq1bnr/ppp1pkpp/8/3pPp2/8/8/PPPPKPPP/RNBQ1BNR w - - 0 4", 0x00fdd303c946bdd9),
+            ("rnbqkbnr/p1pppppp/8/8/PpP4P/8/1P1PPPP1/RNBQKBNR b KQkq c3 0 3", 0x3c8123ea7b067637),
+            ("rnbqkbnr/p1pppppp/8/8/P6P/R1p5/1P1PPPP1/1NBQKBNR b Kkq - 0 4", 0x5c3f9b829b279560),
+        ];
+        for (fen, key) in cases.iter() {
+            assert_eq!(Board::from_fen(fen).polyglot_key(), *key, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn key_follows_moves() {
+        let mut board = Board::default();
+        let mut move_list = MoveList::new();
+        let mut attack_info = AttackInfo::new();
+        let san_moves = ["e4", "d5", "e5", "f5", "Ke2", "Kf7"];
+        for san in san_moves.iter() {
+            let board_move = board.parse_san(san, &mut move_list, &mut attack_info).unwrap();
+            board.do_move(&board_move);
+        }
+        assert_eq!(board.polyglot_key(), 0x00fdd303c946bdd9);
+    }
+}
@@ -0,0 +1,104 @@
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, BufWriter, Write};
+
+use crate::book::book_builder::BookBuilder;
+use crate::cli::perft_command::parse_value;
+use crate::pgn::pgn_reader::PgnReader;
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct BookCommand {
+    pub pgn_path: String,
+    pub book_path: String,
+    pub max_ply: usize,
+    pub min_games: u32,
+    // Percentage of the points scored by the move
+    pub min_score: u32,
+}
+
+impl BookCommand {
+    pub const NAME: &'static str = "book";
+    pub const USAGE: &'static str = "usage: urubu book <pgn file> <book file> [--plies <plies>] [--min-games <games>] [--min-score <percent>]";
+
+    pub fn parse(args: &[String]) -> Result<BookCommand, String> {
+        let path = |index: usize, name: &str| -> Result<String, String> {
+            args.get(index).filter(|arg| !arg.starts_with("--")).cloned().ok_or(format!("missing {}", name))
+        };
+        let mut result = BookCommand {
+            pgn_path: path(0, "pgn file")?,
+            book_path: path(1, "book file")?,
+            max_ply: BookBuilder::DEFAULT_MAX_PLY,
+            min_games: 1,
+            min_score: 0,
+        };
+        let mut index = 2;
+        while index < args.len() {
+            let value = args.get(index + 1);
+            match args[index].as_str() {
+                "--plies" => result.max_ply = parse_value(value, "--plies")?,
+                "--min-games" => result.min_games = parse_value(value, "--min-games")?,
+                "--min-score" => result.min_score = parse_value(value, "--min-score")?,
+                arg => return Err(format!("unknown argument {}", arg)),
+            }
+            index += 2;
+        }
+        if result.min_score > 100 {
+            return Err("invalid value for --min-score".to_string());
+        }
+        Ok(result)
+    }
+
+    pub fn run<W: Write>(&self, output: &mut W) -> io::Result<()> {
+        let mut builder = BookBuilder::new(self.max_ply);
+        builder.set_min_games(self.min_games);
+        builder.set_min_score(self.min_score as f64 / 100.0);
+
+        let mut skipped = 0;
+        for game in PgnReader::new(BufReader::new(File::open(&self.pgn_path)?)) {
+            match game.and_then(|game| builder.add_game(&game)) {
+                Ok(true) => {}
+                Ok(false) => skipped += 1,
+                Err(error) => {
+                    skipped += 1;
+                    writeln!(output, "skipped game: {}", error)?;
+                }
+            }
+        }
+        let entries = builder.write(&mut BufWriter::new(File::create(&self.book_path)?))?;
+        writeln!(output, "Games {}, skipped {}, entries {}", builder.games(), skipped, entries)?;
+        output.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn parse() {
+        let command = BookCommand::parse(&args("games.pgn book.bin --plies 16 --min-games 3 --min-score 45")).unwrap();
+        assert_eq!(command, BookCommand {
+            pgn_path: "games.pgn".to_string(),
+            book_path: "book.bin".to_string(),
+            max_ply: 16,
+            min_games: 3,
+            min_score: 45,
+        });
+        let command = BookCommand::parse(&args("games.pgn book.bin")).unwrap();
+        assert_eq!(command.max_ply, BookBuilder::DEFAULT_MAX_PLY);
+        assert_eq!(command.min_games, 1);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(BookCommand::parse(&args("games.pgn")).is_err());
+        assert!(BookCommand::parse(&args("games.pgn --plies 3")).is_err());
+        assert!(BookCommand::parse(&args("games.pgn book.bin --min-score 101")).is_err());
+        assert!(BookCommand::parse(&args("games.pgn book.bin --plies")).is_err());
+        assert!(BookCommand::parse(&args("games.pgn book.bin --foo 1")).is_err());
+    }
+}
@@ -0,0 +1,81 @@
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, Write};
+use std::time::Duration;
+
+use crate::cli::perft_command::parse_value;
+use crate::epd::epd_runner::{EpdRunner, SearchLimit};
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct EpdCommand {
+    pub path: String,
+    pub limit: SearchLimit,
+    pub hash_mb: usize,
+}
+
+impl EpdCommand {
+    pub const NAME: &'static str = "epd";
+    pub const USAGE: &'static str = "usage: urubu epd <file> [--depth <depth> | --nodes <nodes> | --time <ms>] [--hash <MB>]";
+
+    const DEFAULT_TIME_MS: u64 = 1000;
+    const DEFAULT_HASH_MB: usize = 16;
+
+    pub fn parse(args: &[String]) -> Result<EpdCommand, String> {
+        let mut result = EpdCommand {
+            path: args.first().filter(|arg| !arg.starts_with("--")).ok_or("missing epd file")?.clone(),
+            limit: SearchLimit::Time(Duration::from_millis(EpdCommand::DEFAULT_TIME_MS)),
+            hash_mb: EpdCommand::DEFAULT_HASH_MB,
+        };
+        let mut index = 1;
+        while index < args.len() {
+            let value = args.get(index + 1);
+            match args[index].as_str() {
+                "--depth" => result.limit = SearchLimit::Depth(parse_value(value, "--depth")?),
+                "--nodes" => result.limit = SearchLimit::Nodes(parse_value(value, "--nodes")?),
+                "--time" => result.limit = SearchLimit::Time(Duration::from_millis(parse_value(value, "--time")?)),
+                "--hash" => result.hash_mb = parse_value(value, "--hash")?,
+                arg => return Err(format!("unknown argument {}", arg)),
+            }
+            index += 2;
+        }
+        Ok(result)
+    }
+
+    pub fn run<W: Write>(&self, output: &mut W) -> io::Result<()> {
+        let file = File::open(&self.path)?;
+        let mut runner = EpdRunner::new(self.limit);
+        runner.resize_transposition_table(self.hash_mb);
+        runner.run(BufReader::new(file), output)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn parse() {
+        let command = EpdCommand::parse(&args("wac.epd --nodes 5000 --hash 32")).unwrap();
+        assert_eq!(command, EpdCommand {
+            path: "wac.epd".to_string(),
+            limit: SearchLimit::Nodes(5000),
+            hash_mb: 32,
+        });
+        let command = EpdCommand::parse(&args("wac.epd")).unwrap();
+        assert_eq!(command.limit, SearchLimit::Time(Duration::from_millis(EpdCommand::DEFAULT_TIME_MS)));
+        assert_eq!(EpdCommand::parse(&args("wac.epd --depth 7")).unwrap().limit, SearchLimit::Depth(7));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(EpdCommand::parse(&[]).is_err());
+        assert!(EpdCommand::parse(&args("--depth 3")).is_err());
+        assert!(EpdCommand::parse(&args("wac.epd --time")).is_err());
+        assert!(EpdCommand::parse(&args("wac.epd --foo 1")).is_err());
+    }
+}
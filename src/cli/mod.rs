@@ -0,0 +1,3 @@
+pub mod book_command;
+pub mod epd_command;
+pub mod perft_command;
@@ -0,0 +1,156 @@
+use std::io;
+use std::io::Write;
+use std::time::Instant;
+
+use crate::advanced::board::Board;
+use crate::advanced::perft::Perft;
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct PerftCommand {
+    pub fen: Option<String>,
+    pub depth: u8,
+    pub divide: bool,
+    pub hash_mb: usize,
+    pub threads: usize,
+}
+
+impl PerftCommand {
+    pub const NAME: &'static str = "perft";
+    pub const USAGE: &'static str = "usage: urubu perft [--fen <fen>] [--depth <depth>] [--divide] [--hash <MB>] [--threads <threads>]";
+
+    const DEFAULT_DEPTH: u8 = 5;
+
+    // Arguments after the subcommand name, the fen may be split over several arguments
+    pub fn parse(args: &[String]) -> Result<PerftCommand, String> {
+        let mut result = PerftCommand {
+            fen: None,
+            depth: PerftCommand::DEFAULT_DEPTH,
+            divide: false,
+            hash_mb: 0,
+            threads: 1,
+        };
+        let mut index = 0;
+        while index < args.len() {
+            match args[index].as_str() {
+                "--fen" => {
+                    let fen_tokens: Vec<&str> = args[index + 1..].iter()
+                        .take_while(|arg| !arg.starts_with("--"))
+                        .map(|arg| arg.as_str())
+                        .collect();
+                    if fen_tokens.is_empty() {
+                        return Err("missing value for --fen".to_string());
+                    }
+                    index += fen_tokens.len();
+                    let fen = fen_tokens.join(" ");
+                    Board::try_from_fen(&fen).map_err(|error| format!("invalid fen {}", error))?;
+                    result.fen = Some(fen);
+                }
+                "--depth" => {
+                    result.depth = parse_value(args.get(index + 1), "--depth")?;
+                    index += 1;
+                }
+                "--hash" => {
+                    result.hash_mb = parse_value(args.get(index + 1), "--hash")?;
+                    index += 1;
+                }
+                "--threads" => {
+                    result.threads = parse_value(args.get(index + 1), "--threads")?;
+                    if result.threads == 0 {
+                        return Err("invalid value for --threads".to_string());
+                    }
+                    index += 1;
+                }
+                "--divide" => result.divide = true,
+                arg => return Err(format!("unknown argument {}", arg)),
+            }
+            index += 1;
+        }
+        Ok(result)
+    }
+
+    pub fn run<W: Write>(&self, output: &mut W) -> io::Result<()> {
+        // Checked when parsed
+        let mut board = match &self.fen {
+            Some(fen) => Board::from_fen(fen),
+            None => Board::default(),
+        };
+        let start = Instant::now();
+
+        let nodes = if self.divide {
+            let divide = if self.threads > 1 {
+                Perft::divide_parallel(&board, self.depth, self.threads, self.hash_mb)
+            } else {
+                Perft::with_hash(self.hash_mb).divide(&mut board, self.depth)
+            };
+            for (board_move, nodes) in divide.iter() {
+                writeln!(output, "{}: {}", board_move.to_string(), nodes)?;
+            }
+            writeln!(output)?;
+            divide.iter().map(|(_, nodes)| nodes).sum()
+        } else if self.threads > 1 && self.depth > 0 {
+            Perft::divide_parallel(&board, self.depth, self.threads, self.hash_mb).iter().map(|(_, nodes)| nodes).sum()
+        } else {
+            Perft::with_hash(self.hash_mb).perft(&mut board, self.depth)
+        };
+
+        let elapsed = start.elapsed();
+        let nps = (nodes as f64 / elapsed.as_secs_f64().max(0.001)) as u64;
+        writeln!(output, "Nodes searched: {}", nodes)?;
+        writeln!(output, "Time: {} ms, {} nps", elapsed.as_millis(), nps)?;
+        output.flush()
+    }
+}
+
+pub fn parse_value<T: std::str::FromStr>(value: Option<&String>, name: &str) -> Result<T, String> {
+    value.and_then(|value| value.parse().ok())
+        .ok_or_else(|| format!("invalid value for {}", name))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn parse() {
+        let command = PerftCommand::parse(&args("--fen 8/8/8/8/8/8/8/K6k w - - 0 1 --depth 3 --divide --hash 16 --threads 4")).unwrap();
+        assert_eq!(command, PerftCommand {
+            fen: Some("8/8/8/8/8/8/8/K6k w - - 0 1".to_string()),
+            depth: 3,
+            divide: true,
+            hash_mb: 16,
+            threads: 4,
+        });
+        let command = PerftCommand::parse(&[]).unwrap();
+        assert_eq!(command.fen, None);
+        assert_eq!(command.depth, PerftCommand::DEFAULT_DEPTH);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(PerftCommand::parse(&args("--depth x")).is_err());
+        assert!(PerftCommand::parse(&args("--fen")).is_err());
+        assert_eq!(PerftCommand::parse(&args("--fen 8/8/8/8/8/8/8/K7 w - - 0 1 --depth 3")),
+                   Err("invalid fen missing king for b".to_string()));
+        assert!(PerftCommand::parse(&args("--foo")).is_err());
+        assert!(PerftCommand::parse(&args("--threads 0")).is_err());
+    }
+
+    #[test]
+    fn run_divide() {
+        let command = PerftCommand::parse(&args("--depth 2 --divide --hash 1")).unwrap();
+        let mut output = Vec::new();
+        command.run(&mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("e2e4: 20\n"));
+        assert!(output.contains("Nodes searched: 400\n"));
+
+        let command = PerftCommand::parse(&args("--depth 3 --threads 2")).unwrap();
+        let mut output = Vec::new();
+        command.run(&mut output).unwrap();
+        assert!(String::from_utf8(output).unwrap().contains("Nodes searched: 8902\n"));
+    }
+}
@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use crate::advanced::attack_info::AttackInfo;
+use crate::advanced::board::Board;
+use crate::advanced::board_factory::FenError;
+use crate::advanced::move_list::MoveList;
+use crate::advanced::san::SanError;
+use crate::types::board_move::BoardMove;
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum EpdError {
+    MissingField(&'static str),
+    InvalidFen(FenError),
+    UnterminatedString(String),
+    InvalidOperand(String, String),
+    InvalidMove(String, SanError),
+}
+
+impl fmt::Display for EpdError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EpdError::MissingField(field) => write!(formatter, "missing {}", field),
+            EpdError::InvalidFen(error) => write!(formatter, "{}", error),
+            EpdError::UnterminatedString(operation) => write!(formatter, "unterminated string in '{}'", operation),
+            EpdError::InvalidOperand(opcode, operand) => write!(formatter, "invalid operand '{}' for {}", operand, opcode),
+            EpdError::InvalidMove(opcode, error) => write!(formatter, "{}: {}", opcode, error),
+        }
+    }
+}
+
+impl Error for EpdError {}
+
+// A position and its operations, operands keep their order and lose their quotes
+#[derive(Clone)]
+pub struct EpdPosition {
+    pub board: Board,
+    pub operations: HashMap<String, Vec<String>>,
+}
+
+impl EpdPosition {
+    pub const OPCODE_BEST_MOVE: &'static str = "bm";
+    pub const OPCODE_AVOID_MOVE: &'static str = "am";
+    pub const OPCODE_ID: &'static str = "id";
+    pub const OPCODE_COMMENT: &'static str = "c0";
+    pub const OPCODE_DEPTH: &'static str = "acd";
+    pub const OPCODE_EVALUATION: &'static str = "ce";
+    pub const OPCODE_VARIATION: &'static str = "pv";
+    pub const OPCODE_HALFMOVE_CLOCK: &'static str = "hmvc";
+    pub const OPCODE_FULLMOVE_NUMBER: &'static str = "fmvn";
+    // Perft counts are stored as D1 to Dn
+    pub const PERFT_PREFIX: char = 'D';
+
+    const FIELDS: [&'static str; 4] = ["piece placement", "side to move", "castling rights", "en passant square"];
+
+    pub fn parse(line: &str) -> Result<EpdPosition, EpdError> {
+        let line = line.trim();
+        let mut rest = line;
+        let mut fields = Vec::with_capacity(EpdPosition::FIELDS.len());
+        for field in EpdPosition::FIELDS.iter() {
+            rest = rest.trim_start();
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            if end == 0 {
+                return Err(EpdError::MissingField(field));
+            }
+            fields.push(&rest[..end]);
+            rest = &rest[end..];
+        }
+
+        let operations = EpdPosition::parse_operations(rest)?;
+        let clock = |opcode: &str, default: &str| -> String {
+            operations.get(opcode)
+                .and_then(|operands| operands.first())
+                .map_or(default.to_string(), |operand| operand.clone())
+        };
+        let fen = format!("{} {} {}", fields.join(" "),
+                          clock(EpdPosition::OPCODE_HALFMOVE_CLOCK, "0"),
+                          clock(EpdPosition::OPCODE_FULLMOVE_NUMBER, "1"));
+        let board = Board::try_from_fen(&fen).map_err(EpdError::InvalidFen)?;
+        Ok(EpdPosition { board, operations })
+    }
+
+    // Operations end with ';', which is allowed inside quoted strings
+    fn parse_operations(text: &str) -> Result<HashMap<String, Vec<String>>, EpdError> {
+        let mut result = HashMap::new();
+        let mut tokens: Vec<String> = Vec::new();
+        let mut token = String::new();
+        let mut chars = text.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => {
+                    let mut closed = false;
+                    for c in chars.by_ref() {
+                        if c == '"' {
+                            closed = true;
+                            break;
+                        }
+                        token.push(c);
+                    }
+                    if !closed {
+                        return Err(EpdError::UnterminatedString(text.trim().to_string()));
+                    }
+                    tokens.push(token.clone());
+                    token.clear();
+                }
+                ';' => {
+                    EpdPosition::finish_token(&mut token, &mut tokens);
+                    if !tokens.is_empty() {
+                        let opcode = tokens.remove(0);
+                        result.insert(opcode, tokens.clone());
+                        tokens.clear();
+                    }
+                }
+                _ if c.is_whitespace() => EpdPosition::finish_token(&mut token, &mut tokens),
+                _ => token.push(c),
+            }
+        }
+        // Tolerate a missing ';' after the last operation
+        EpdPosition::finish_token(&mut token, &mut tokens);
+        if !tokens.is_empty() {
+            let opcode = tokens.remove(0);
+            result.insert(opcode, tokens);
+        }
+        Ok(result)
+    }
+
+    fn finish_token(token: &mut String, tokens: &mut Vec<String>) {
+        if !token.is_empty() {
+            tokens.push(token.clone());
+            token.clear();
+        }
+    }
+
+    pub fn operation(&self, opcode: &str) -> Option<&[String]> {
+        self.operations.get(opcode).map(|operands| operands.as_slice())
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.first_operand(EpdPosition::OPCODE_ID)
+    }
+
+    pub fn comment(&self) -> Option<&str> {
+        self.first_operand(EpdPosition::OPCODE_COMMENT)
+    }
+
+    fn first_operand(&self, opcode: &str) -> Option<&str> {
+        self.operation(opcode)
+            .and_then(|operands| operands.first())
+            .map(|operand| operand.as_str())
+    }
+
+    pub fn best_moves(&self) -> Result<Vec<BoardMove>, EpdError> {
+        self.moves(EpdPosition::OPCODE_BEST_MOVE)
+    }
+
+    pub fn avoid_moves(&self) -> Result<Vec<BoardMove>, EpdError> {
+        self.moves(EpdPosition::OPCODE_AVOID_MOVE)
+    }
+
+    // Every operand is a SAN move on the position
+    fn moves(&self, opcode: &str) -> Result<Vec<BoardMove>, EpdError> {
+        let mut move_list = MoveList::new();
+        let mut attack_info = AttackInfo::new();
+        self.operation(opcode).unwrap_or(&[]).iter()
+            .map(|san| self.board.parse_san(san, &mut move_list, &mut attack_info).map_err(|error| EpdError::InvalidMove(opcode.to_string(), error)))
+            .collect()
+    }
+
+    // Moves of the predicted variation are played one after the other
+    pub fn principal_variation(&self) -> Result<Vec<BoardMove>, EpdError> {
+        let mut board = self.board.clone_position();
+        let mut move_list = MoveList::new();
+        let mut attack_info = AttackInfo::new();
+        let mut result = Vec::new();
+        for san in self.operation(EpdPosition::OPCODE_VARIATION).unwrap_or(&[]).iter() {
+            let board_move = board.parse_san(san, &mut move_list, &mut attack_info)
+                .map_err(|error| EpdError::InvalidMove(EpdPosition::OPCODE_VARIATION.to_string(), error))?;
+            board.do_move(&board_move);
+            result.push(board_move);
+        }
+        Ok(result)
+    }
+
+    pub fn analysis_depth(&self) -> Result<Option<u8>, EpdError> {
+        self.parse_operand(EpdPosition::OPCODE_DEPTH)
+    }
+
+    // Centipawns from the side to move point of view
+    pub fn evaluation(&self) -> Result<Option<i32>, EpdError> {
+        self.parse_operand(EpdPosition::OPCODE_EVALUATION)
+    }
+
+    fn parse_operand<T: std::str::FromStr>(&self, opcode: &str) -> Result<Option<T>, EpdError> {
+        match self.first_operand(opcode) {
+            Some(operand) => operand.parse()
+                .map(Some)
+                .map_err(|_| EpdError::InvalidOperand(opcode.to_string(), operand.to_string())),
+            None => Ok(None),
+        }
+    }
+
+    // Sorted by depth
+    pub fn perft_counts(&self) -> Result<Vec<(u8, u64)>, EpdError> {
+        let mut result = Vec::new();
+        for (opcode, operands) in self.operations.iter() {
+            if let Some(Ok(depth)) = opcode.strip_prefix(EpdPosition::PERFT_PREFIX).map(|depth| depth.parse::<u8>()) {
+                let nodes = operands.first()
+                    .and_then(|nodes| nodes.parse().ok())
+                    .ok_or_else(|| EpdError::InvalidOperand(opcode.clone(), operands.join(" ")))?;
+                result.push((depth, nodes));
+            }
+        }
+        result.sort_unstable();
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::square::Square;
+
+    #[test]
+    fn parse() {
+        let position = EpdPosition::parse("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - \
+            bm Bb5 Bc4; am a3; id \"test; 1\"; c0 \"Ruy Lopez\"; acd 12; ce -15; pv Bb5 a6 Ba4;").unwrap();
+        assert_eq!(position.board.to_fen(), "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 0 1");
+        assert_eq!(position.id(), Some("test; 1"));
+        assert_eq!(position.comment(), Some("Ruy Lopez"));
+        assert_eq!(position.best_moves(), Ok(vec![
+            BoardMove::build_normal(&Square::F1, &Square::B5),
+            BoardMove::build_normal(&Square::F1, &Square::C4),
+        ]));
+        assert_eq!(position.avoid_moves(), Ok(vec![BoardMove::build_normal(&Square::A2, &Square::A3)]));
+        assert_eq!(position.analysis_depth(), Ok(Some(12)));
+        assert_eq!(position.evaluation(), Ok(Some(-15)));
+        assert_eq!(position.principal_variation().unwrap().len(), 3);
+        assert_eq!(position.operation("zz"), None);
+    }
+
+    #[test]
+    fn clocks_and_perft() {
+        let position = EpdPosition::parse("4k3/8/8/8/8/8/8/4K2R w K - hmvc 7; fmvn 30; D1 15; D2 66 ;D3 1197").unwrap();
+        assert_eq!(position.board.to_fen(), "4k3/8/8/8/8/8/8/4K2R w K - 7 30");
+        assert_eq!(position.perft_counts(), Ok(vec![(1, 15), (2, 66), (3, 1197)]));
+        assert_eq!(position.best_moves(), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(EpdPosition::parse("4k3/8/8/8/8/8/8/4K3 w -").err(), Some(EpdError::MissingField("en passant square")));
+        assert_eq!(EpdPosition::parse("4k3/8/8/8/8/8/8/4K3 w - - id \"open").err(),
+                   Some(EpdError::UnterminatedString("id \"open".to_string())));
+        assert_eq!(EpdPosition::parse("4k3/8/8/8/8/8/8/8 w - -").err(), Some(EpdError::InvalidFen(FenError::MissingKing(crate::types::color::Color::White))));
+        let position = EpdPosition::parse("4k3/8/8/8/8/8/8/4K3 w - - acd x; bm Kd3;").unwrap();
+        assert_eq!(position.analysis_depth(), Err(EpdError::InvalidOperand("acd".to_string(), "x".to_string())));
+        assert!(position.best_moves().is_err());
+    }
+}
@@ -0,0 +1,184 @@
+use std::io;
+use std::io::{BufRead, Write};
+use std::time::Duration;
+
+use crate::advanced::attack_info::AttackInfo;
+use crate::advanced::move_list::MoveList;
+use crate::epd::epd_position::{EpdError, EpdPosition};
+use crate::search::searcher::{SearchResult, Searcher};
+use crate::types::board_move::BoardMove;
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum SearchLimit {
+    Depth(u8),
+    Nodes(u64),
+    Time(Duration),
+}
+
+#[derive(Clone, Debug)]
+pub struct EpdOutcome {
+    pub id: Option<String>,
+    pub best_move: String,
+    // None when the position has neither bm nor am
+    pub solved: Option<bool>,
+    pub result: SearchResult,
+}
+
+#[derive(PartialEq, Copy, Clone, Debug, Default)]
+pub struct EpdSummary {
+    pub positions: usize,
+    pub solved: usize,
+    pub failed: usize,
+    pub errors: usize,
+}
+
+pub struct EpdRunner {
+    searcher: Searcher,
+    limit: SearchLimit,
+    move_list: MoveList,
+    attack_info: AttackInfo,
+}
+
+impl EpdRunner {
+    pub const NO_MOVE: &'static str = "-";
+
+    pub fn new(limit: SearchLimit) -> Self {
+        EpdRunner {
+            searcher: Searcher::new(),
+            limit,
+            move_list: MoveList::new(),
+            attack_info: AttackInfo::new(),
+        }
+    }
+
+    pub fn resize_transposition_table(&mut self, size_mb: usize) {
+        self.searcher.resize_transposition_table(size_mb);
+    }
+
+    // Solved when the move found is one of the bm moves and none of the am moves
+    pub fn run_position(&mut self, position: &EpdPosition) -> Result<EpdOutcome, EpdError> {
+        let best_moves = position.best_moves()?;
+        let avoid_moves = position.avoid_moves()?;
+
+        let (max_depth, node_limit, time_limit) = match self.limit {
+            SearchLimit::Depth(depth) => (depth, None, None),
+            SearchLimit::Nodes(nodes) => (u8::MAX, Some(nodes), None),
+            SearchLimit::Time(time) => (u8::MAX, None, Some(time)),
+        };
+        self.searcher.set_node_limit(node_limit);
+        self.searcher.set_time_limit(time_limit);
+        // Positions are independent, earlier ones should not help
+        self.searcher.clear_transposition_table();
+        self.searcher.clear_history();
+        let result = self.searcher.search(&position.board, max_depth);
+
+        let solved = if best_moves.is_empty() && avoid_moves.is_empty() {
+            None
+        } else {
+            Some((best_moves.is_empty() || best_moves.contains(&result.best_move)) && !avoid_moves.contains(&result.best_move))
+        };
+        // Mated and stalemated positions have no move to show
+        let best_move = if result.best_move == BoardMove::NONE {
+            EpdRunner::NO_MOVE.to_string()
+        } else {
+            position.board.move_to_san(&result.best_move, &mut self.move_list, &mut self.attack_info)
+        };
+        Ok(EpdOutcome {
+            id: position.id().map(|id| id.to_string()),
+            best_move,
+            solved,
+            result,
+        })
+    }
+
+    // One position per line, blank lines and lines starting with '#' are skipped
+    pub fn run<R: BufRead, W: Write>(&mut self, input: R, output: &mut W) -> io::Result<EpdSummary> {
+        let mut summary = EpdSummary::default();
+        for line in input.lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            summary.positions += 1;
+            let outcome = EpdPosition::parse(line).and_then(|position| self.run_position(&position));
+            match outcome {
+                Ok(outcome) => {
+                    let status = match outcome.solved {
+                        Some(true) => {
+                            summary.solved += 1;
+                            "solved"
+                        }
+                        Some(false) => {
+                            summary.failed += 1;
+                            "failed"
+                        }
+                        None => "unscored",
+                    };
+                    writeln!(output, "{} {} {} (depth {}, nodes {})",
+                             outcome.id.as_deref().unwrap_or(line), outcome.best_move, status,
+                             outcome.result.depth, outcome.result.nodes)?;
+                }
+                Err(error) => {
+                    summary.errors += 1;
+                    writeln!(output, "{} error: {}", line, error)?;
+                }
+            }
+        }
+        writeln!(output, "Solved {} of {}, failed {}, errors {}",
+                 summary.solved, summary.positions, summary.failed, summary.errors)?;
+        output.flush()?;
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SUITE: &str = "# Tactics
+6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8#; id \"mate\";
+4k3/8/8/8/8/8/8/R3K3 w - - am Ra8+; id \"avoid\";
+4k3/8/8/3q4/8/8/3R4/4K3 w - - bm Kf1; id \"wrong\";
+
+4k3/8/8/8/8/8/8/4K3 w - - id \"unscored\";
+4k3/8/8/8/8/8/8/4K3 w - - bm Qd4; id \"illegal\";
+";
+
+    #[test]
+    fn run_suite() {
+        let mut runner = EpdRunner::new(SearchLimit::Depth(3));
+        let mut output = Vec::new();
+        let summary = runner.run(SUITE.as_bytes(), &mut output).unwrap();
+        assert_eq!(summary, EpdSummary { positions: 5, solved: 2, failed: 1, errors: 1 });
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("mate Ra8# solved"));
+        assert!(output.contains("wrong Rxd5 failed"));
+        assert!(output.contains("unscored"));
+        assert!(output.ends_with("Solved 2 of 5, failed 1, errors 1\n"));
+    }
+
+    #[test]
+    fn limits() {
+        let position = EpdPosition::parse("6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8#;").unwrap();
+        let mut runner = EpdRunner::new(SearchLimit::Nodes(2000));
+        let outcome = runner.run_position(&position).unwrap();
+        assert_eq!(outcome.solved, Some(true));
+        assert!(outcome.result.nodes <= 2000);
+
+        let mut runner = EpdRunner::new(SearchLimit::Time(Duration::from_millis(10)));
+        assert_eq!(runner.run_position(&position).unwrap().solved, Some(true));
+    }
+
+    #[test]
+    fn no_legal_moves() {
+        let suite = "R5k1/5ppp/8/8/8/8/8/6K1 b - - id \"mated\";\n7k/5Q2/6K1/8/8/8/8/8 b - - id \"stalemate\";\n";
+        let mut runner = EpdRunner::new(SearchLimit::Depth(3));
+        let mut output = Vec::new();
+        let summary = runner.run(suite.as_bytes(), &mut output).unwrap();
+        assert_eq!(summary, EpdSummary { positions: 2, solved: 0, failed: 0, errors: 0 });
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("mated - unscored"));
+        assert!(output.contains("stalemate - unscored"));
+    }
+}
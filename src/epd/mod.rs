@@ -0,0 +1,2 @@
+pub mod epd_position;
+pub mod epd_runner;
@@ -0,0 +1,102 @@
+use crate::advanced::board::Board;
+use crate::eval::psqt::{MATERIAL_EG, MATERIAL_MG, PSQT_EG, PSQT_MG};
+use crate::types::color::Color;
+use crate::types::piece_type::PieceType;
+
+pub const PHASE_WEIGHT: [i32; PieceType::NUM_PIECE_TYPES] = [0, 0, 1, 1, 2, 4, 0];
+pub const MAX_PHASE: i32 = 24;
+
+// Returns MAX_PHASE for the initial material and 0 for bare kings and pawns
+pub fn game_phase(board: &Board) -> i32 {
+    let mut phase = 0;
+    for piece_type in PieceType::PIECE_TYPES.iter() {
+        let pieces = board.piece_bitboard(&Color::White, piece_type)
+            .union(&board.piece_bitboard(&Color::Black, piece_type));
+        phase += PHASE_WEIGHT[piece_type.to_usize()] * pieces.count() as i32;
+    }
+    phase.min(MAX_PHASE)
+}
+
+// Centipawn score relative to the side to move
+pub fn evaluate(board: &Board) -> i32 {
+    let mut score_mg = 0;
+    let mut score_eg = 0;
+
+    for color in Color::COLORS.iter() {
+        let multiplier = color.multiplier() as i32;
+        for piece_type in PieceType::PIECE_TYPES.iter() {
+            for square in board.piece_bitboard(color, piece_type).iterator() {
+                let relative_square = square.relative(color).to_usize();
+                score_mg += multiplier * (MATERIAL_MG[piece_type.to_usize()] + PSQT_MG[piece_type.to_usize()][relative_square]);
+                score_eg += multiplier * (MATERIAL_EG[piece_type.to_usize()] + PSQT_EG[piece_type.to_usize()][relative_square]);
+            }
+        }
+    }
+
+    let phase = game_phase(board);
+    let score = (score_mg * phase + score_eg * (MAX_PHASE - phase)) / MAX_PHASE;
+    score * board.color_to_move.multiplier() as i32
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Swaps colors and flips the board vertically
+    fn mirror_fen(fen: &str) -> String {
+        let mut tokens = fen.split(' ');
+        let pieces: Vec<String> = tokens.next().unwrap().split('/').rev()
+            .map(|rank| rank.chars().map(swap_case).collect())
+            .collect();
+        let color = if tokens.next().unwrap() == "w" { "b" } else { "w" };
+        let castling: String = tokens.next().unwrap().chars().map(swap_case).collect();
+        format!("{} {} {} -", pieces.join("/"), color, castling)
+    }
+
+    fn swap_case(c: char) -> char {
+        if c.is_uppercase() {
+            c.to_ascii_lowercase()
+        } else {
+            c.to_ascii_uppercase()
+        }
+    }
+
+    #[test]
+    fn start_position_is_balanced() {
+        assert_eq!(evaluate(&Board::default()), 0);
+    }
+
+    #[test]
+    fn phase() {
+        assert_eq!(game_phase(&Board::default()), MAX_PHASE);
+        assert_eq!(game_phase(&Board::from_fen("4k3/pppp4/8/8/8/8/4PPPP/4K3 w - -")), 0);
+        assert_eq!(game_phase(&Board::from_fen("4k3/8/8/8/8/8/8/3QK3 w - -")), 4);
+    }
+
+    #[test]
+    fn side_to_move_relative() {
+        let white_to_move = evaluate(&Board::from_fen("4k3/8/8/8/8/8/8/3QK3 w - -"));
+        let black_to_move = evaluate(&Board::from_fen("4k3/8/8/8/8/8/8/3QK3 b - -"));
+        assert!(white_to_move > 800);
+        assert_eq!(white_to_move, -black_to_move);
+    }
+
+    #[test]
+    fn mirrored_positions_are_equal() {
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ -",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - -",
+        ];
+        for fen in fens.iter() {
+            assert_eq!(evaluate(&Board::from_fen(fen)), evaluate(&Board::from_fen(&mirror_fen(fen))));
+        }
+    }
+
+    #[test]
+    fn centralized_knight_is_better() {
+        let center = evaluate(&Board::from_fen("4k3/8/8/8/3N4/8/8/4K3 w - -"));
+        let corner = evaluate(&Board::from_fen("4k3/8/8/8/8/8/8/N3K3 w - -"));
+        assert!(center > corner);
+    }
+}
@@ -67,6 +67,7 @@ pub fn generate_bitboard_file() -> io::Result<()> {
     write_2d_bitboard_array(&mut file, "PAWN_MOVES", &init_pawn_moves())?;
     write_2d_bitboard_array(&mut file, "PAWN_DOUBLE_MOVES", &init_pawn_double_moves())?;
     write_2d_bitboard_array(&mut file, "PINNED_MASK", &init_pinned_mask())?;
+    write_2d_bitboard_array(&mut file, "PASSED_PAWN_MASK", &init_passed_pawn_masks())?;
     Ok(())
 }
 
@@ -276,6 +277,32 @@ fn init_pawn_double_move(square: &Square, color: &Color) -> Bitboard {
     return Bitboard::EMPTY;
 }
 
+fn init_passed_pawn_masks() -> [[Bitboard; Square::NUM_SQUARES]; Color::NUM_COLORS] {
+    let mut result = [[Bitboard::EMPTY; Square::NUM_SQUARES]; Color::NUM_COLORS];
+    for square in Square::SQUARES.iter() {
+        result[Color::White.to_usize()][square.to_usize()] = init_passed_pawn_mask(square, &Color::White);
+        result[Color::Black.to_usize()][square.to_usize()] = init_passed_pawn_mask(square, &Color::Black);
+    }
+    result
+}
+
+// Squares ahead of the pawn on its file and the adjacent ones
+fn init_passed_pawn_mask(square: &Square, color: &Color) -> Bitboard {
+    let mut result = Bitboard::EMPTY;
+    let mut front = square.offset(PAWN_FORWARD[color.to_usize()]);
+    while let Some(front_square) = front {
+        result = result.with_square(&front_square);
+        if front_square.to_file() != file::File::FILE_A {
+            result = result.with_square(&Square(front_square.0 + WEST));
+        }
+        if front_square.to_file() != file::File::FILE_H {
+            result = result.with_square(&Square(front_square.0 + EAST));
+        }
+        front = front_square.offset(PAWN_FORWARD[color.to_usize()]);
+    }
+    result
+}
+
 fn init_pinned_mask() -> [[Bitboard; Square::NUM_SQUARES]; Square::NUM_SQUARES] {
     let mut result = [[Bitboard::EMPTY; Square::NUM_SQUARES]; Square::NUM_SQUARES];
     let direction_array: [[i8; 1]; 8] = [[7], [9], [1], [8], [-7], [-9], [-1], [-8]];
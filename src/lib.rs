@@ -1,4 +1,10 @@
 pub mod advanced;
+pub mod book;
+pub mod cli;
+pub mod epd;
+pub mod eval;
+pub mod pgn;
 pub mod types;
 pub mod search;
-pub mod utils;
\ No newline at end of file
+pub mod uci;
+pub mod utils;
@@ -1,17 +1,46 @@
-use urubu::advanced::perft::Perft;
-use urubu::advanced::board::Board;
-use std::time::Instant;
-use urubu::types::board_move::BoardMove;
-use urubu::types::square::Square;
+use std::env;
+use std::io;
+use std::process;
 
-fn main() {
-    println!("Hello, world!");
-    let mut board = Board::default();
-    let before_time = Instant::now();
-    let nodes = Perft::new().perft(&mut board, 7);
-    let after_time = Instant::now();
-    let dur = after_time.duration_since(before_time).as_millis() as u64;
-    println!("perft result {}", nodes);
-    println!("Time taken {} ms", dur);
-    println!("nps {}", (nodes * 1000 /dur));
+use urubu::cli::book_command::BookCommand;
+use urubu::cli::epd_command::EpdCommand;
+use urubu::cli::perft_command::PerftCommand;
+use urubu::uci::uci_engine::UciEngine;
+
+fn main() -> io::Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(|arg| arg.as_str()) == Some(PerftCommand::NAME) {
+        return match PerftCommand::parse(&args[1..]) {
+            Ok(command) => command.run(&mut io::stdout()),
+            Err(message) => {
+                eprintln!("{}", message);
+                eprintln!("{}", PerftCommand::USAGE);
+                process::exit(1);
+            }
+        };
+    }
+    if args.first().map(|arg| arg.as_str()) == Some(EpdCommand::NAME) {
+        return match EpdCommand::parse(&args[1..]) {
+            Ok(command) => command.run(&mut io::stdout()),
+            Err(message) => {
+                eprintln!("{}", message);
+                eprintln!("{}", EpdCommand::USAGE);
+                process::exit(1);
+            }
+        };
+    }
+    if args.first().map(|arg| arg.as_str()) == Some(BookCommand::NAME) {
+        return match BookCommand::parse(&args[1..]) {
+            Ok(command) => command.run(&mut io::stdout()),
+            Err(message) => {
+                eprintln!("{}", message);
+                eprintln!("{}", BookCommand::USAGE);
+                process::exit(1);
+            }
+        };
+    }
+
+    let stdin = io::stdin();
+    let mut engine = UciEngine::new(io::stdout());
+    engine.run(stdin.lock())
 }
@@ -0,0 +1,3 @@
+pub mod pgn_game;
+pub mod pgn_reader;
+pub mod pgn_writer;
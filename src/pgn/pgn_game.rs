@@ -0,0 +1,212 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use crate::advanced::board::Board;
+use crate::advanced::board_factory::FenError;
+use crate::advanced::game::{Game, GameResult};
+use crate::advanced::san::SanError;
+use crate::types::board_move::BoardMove;
+use crate::types::color::Color;
+
+#[derive(Debug)]
+pub enum PgnError {
+    Io(io::Error),
+    InvalidTag(String),
+    InvalidFen(FenError),
+    InvalidMove(u16, SanError),
+    UnexpectedToken(String),
+    UnbalancedVariation,
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PgnError::Io(error) => write!(formatter, "{}", error),
+            PgnError::InvalidTag(tag) => write!(formatter, "invalid tag '{}'", tag),
+            PgnError::InvalidFen(error) => write!(formatter, "invalid fen tag: {}", error),
+            PgnError::InvalidMove(move_number, error) => write!(formatter, "move {}: {}", move_number, error),
+            PgnError::UnexpectedToken(token) => write!(formatter, "unexpected '{}'", token),
+            PgnError::UnbalancedVariation => write!(formatter, "unbalanced variation"),
+        }
+    }
+}
+
+impl Error for PgnError {}
+
+impl From<io::Error> for PgnError {
+    fn from(error: io::Error) -> Self {
+        PgnError::Io(error)
+    }
+}
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum PgnResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    Unknown,
+}
+
+impl PgnResult {
+    pub fn parse(token: &str) -> Option<PgnResult> {
+        match token {
+            "1-0" => Some(PgnResult::WhiteWins),
+            "0-1" => Some(PgnResult::BlackWins),
+            "1/2-1/2" => Some(PgnResult::Draw),
+            "*" => Some(PgnResult::Unknown),
+            _ => None,
+        }
+    }
+
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            PgnResult::WhiteWins => "1-0",
+            PgnResult::BlackWins => "0-1",
+            PgnResult::Draw => "1/2-1/2",
+            PgnResult::Unknown => "*",
+        }
+    }
+
+    // Claimable draws are left open, nobody claimed them yet
+    pub fn from_game_result(game_result: &GameResult) -> PgnResult {
+        match game_result {
+            GameResult::Checkmate(Color::White) => PgnResult::WhiteWins,
+            GameResult::Checkmate(Color::Black) => PgnResult::BlackWins,
+            GameResult::Draw(reason) if !reason.is_claimable() => PgnResult::Draw,
+            _ => PgnResult::Unknown,
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct PgnMove {
+    pub board_move: BoardMove,
+    pub nags: Vec<u8>,
+    pub comment_before: Option<String>,
+    pub comment: Option<String>,
+    // Alternatives to this move, each played from the position before it
+    pub variations: Vec<Vec<PgnMove>>,
+}
+
+impl PgnMove {
+    pub fn new(board_move: BoardMove) -> Self {
+        PgnMove {
+            board_move,
+            nags: Vec::new(),
+            comment_before: None,
+            comment: None,
+            variations: Vec::new(),
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct PgnGame {
+    // Kept in file order
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<PgnMove>,
+    pub result: PgnResult,
+}
+
+impl PgnGame {
+    pub const TAG_FEN: &'static str = "FEN";
+    pub const TAG_SETUP: &'static str = "SetUp";
+    pub const TAG_RESULT: &'static str = "Result";
+
+    pub fn new() -> Self {
+        PgnGame {
+            tags: Vec::new(),
+            moves: Vec::new(),
+            result: PgnResult::Unknown,
+        }
+    }
+
+    pub fn from_moves(board: &Board, moves: &[BoardMove]) -> Self {
+        let mut result = PgnGame::new();
+        let start_fen = board.to_fen();
+        if start_fen != Board::default().to_fen() {
+            result.set_tag(PgnGame::TAG_SETUP, "1");
+            result.set_tag(PgnGame::TAG_FEN, &start_fen);
+        }
+        result.moves = moves.iter().map(|board_move| PgnMove::new(*board_move)).collect();
+        result
+    }
+
+    pub fn from_game(game: &mut Game) -> Self {
+        let mut board = game.board().clone();
+        for board_move in game.moves().iter().rev() {
+            board.undo_move(board_move);
+        }
+        let mut result = PgnGame::from_moves(&board, game.moves());
+        result.set_result(PgnResult::from_game_result(&game.result()));
+        result
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter()
+            .find(|(tag_name, _)| tag_name == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag_name, _)| tag_name == name) {
+            Some(tag) => tag.1 = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    pub fn set_result(&mut self, result: PgnResult) {
+        self.result = result;
+        self.set_tag(PgnGame::TAG_RESULT, result.to_str());
+    }
+
+    pub fn starting_board(&self) -> Result<Board, PgnError> {
+        match self.tag(PgnGame::TAG_FEN) {
+            Some(fen) => Board::try_from_fen(fen).map_err(PgnError::InvalidFen),
+            None => Ok(Board::default()),
+        }
+    }
+
+    // Replays the main line
+    pub fn to_game(&self) -> Result<Game, PgnError> {
+        let mut game = Game::from_board(self.starting_board()?);
+        for pgn_move in self.moves.iter() {
+            if !game.do_move(&pgn_move.board_move) {
+                let board_move = pgn_move.board_move.to_string();
+                return Err(PgnError::InvalidMove(game.board().move_number / 2 + 1, SanError::IllegalMove(board_move)));
+            }
+        }
+        Ok(game)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::square::Square;
+
+    #[test]
+    fn tags() {
+        let mut game = PgnGame::new();
+        assert_eq!(game.tag("White"), None);
+        game.set_tag("White", "Kasparov");
+        game.set_tag("Black", "Karpov");
+        game.set_tag("White", "Carlsen");
+        assert_eq!(game.tag("White"), Some("Carlsen"));
+        assert_eq!(game.tags.len(), 2);
+        game.set_result(PgnResult::Draw);
+        assert_eq!(game.tag("Result"), Some("1/2-1/2"));
+    }
+
+    #[test]
+    fn from_game() {
+        let mut game = Game::from_board(Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 30"));
+        game.do_move(&BoardMove::build_normal(&Square::A1, &Square::A8));
+        let pgn_game = PgnGame::from_game(&mut game);
+        assert_eq!(pgn_game.tag("FEN"), Some("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 30"));
+        assert_eq!(pgn_game.tag("SetUp"), Some("1"));
+        assert_eq!(pgn_game.result, PgnResult::WhiteWins);
+        assert_eq!(pgn_game.to_game().unwrap().board().to_fen(), game.board().to_fen());
+    }
+}
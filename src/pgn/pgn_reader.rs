@@ -0,0 +1,345 @@
+use std::io::BufRead;
+
+use crate::advanced::attack_info::AttackInfo;
+use crate::advanced::board::Board;
+use crate::advanced::move_list::MoveList;
+use crate::pgn::pgn_game::{PgnError, PgnGame, PgnMove, PgnResult};
+
+#[derive(PartialEq, Clone, Debug)]
+enum Token {
+    Tag(String, String),
+    Comment(String),
+    Nag(u8),
+    VariationStart,
+    VariationEnd,
+    Result(PgnResult),
+    Move(String),
+}
+
+// Reads games one at a time, a broken game is reported and skipped
+pub struct PgnReader<R: BufRead> {
+    input: R,
+    line: Vec<char>,
+    index: usize,
+    pending: Option<Token>,
+    move_list: MoveList,
+    attack_info: AttackInfo,
+}
+
+impl<R: BufRead> PgnReader<R> {
+    // Move suffix annotations and their numeric annotation glyphs
+    const SUFFIX_NAGS: [(&'static str, u8); 6] = [("!!", 3), ("??", 4), ("!?", 5), ("?!", 6), ("!", 1), ("?", 2)];
+
+    pub fn new(input: R) -> Self {
+        PgnReader {
+            input,
+            line: Vec::new(),
+            index: 0,
+            pending: None,
+            move_list: MoveList::new(),
+            attack_info: AttackInfo::new(),
+        }
+    }
+
+    fn read_line(&mut self) -> Result<bool, PgnError> {
+        let mut line = String::new();
+        if self.input.read_line(&mut line)? == 0 {
+            return Ok(false);
+        }
+        // Escaped lines are meant for other programs
+        if line.starts_with('%') {
+            line.clear();
+            line.push('\n');
+        }
+        self.line = line.chars().collect();
+        self.index = 0;
+        Ok(true)
+    }
+
+    fn next_char(&mut self) -> Result<Option<char>, PgnError> {
+        while self.index >= self.line.len() {
+            if !self.read_line()? {
+                return Ok(None);
+            }
+        }
+        self.index += 1;
+        Ok(Some(self.line[self.index - 1]))
+    }
+
+    fn peek_char(&self) -> Option<char> {
+        self.line.get(self.index).copied()
+    }
+
+    fn read_until(&mut self, end: char) -> Result<String, PgnError> {
+        let mut result = String::new();
+        while let Some(c) = self.next_char()? {
+            if c == end {
+                return Ok(result);
+            }
+            result.push(c);
+        }
+        Err(PgnError::UnexpectedToken(result))
+    }
+
+    fn read_rest_of_line(&mut self) -> String {
+        let result = self.line[self.index..].iter().collect();
+        self.index = self.line.len();
+        result
+    }
+
+    fn read_tag(&mut self) -> Result<Token, PgnError> {
+        let text = self.read_until(']')?;
+        let invalid = || PgnError::InvalidTag(text.clone());
+        let (name, value) = text.trim().split_once(char::is_whitespace).ok_or_else(invalid)?;
+        let value = value.trim();
+        if name.is_empty() || value.len() < 2 || !value.starts_with('"') || !value.ends_with('"') {
+            return Err(invalid());
+        }
+        let value = value[1..value.len() - 1].replace("\\\"", "\"").replace("\\\\", "\\");
+        Ok(Token::Tag(name.to_string(), value))
+    }
+
+    fn read_symbol(&mut self, first: char) -> String {
+        let mut result = first.to_string();
+        while let Some(c) = self.peek_char() {
+            if c.is_whitespace() || "{}()[];$".contains(c) {
+                break;
+            }
+            result.push(c);
+            self.index += 1;
+        }
+        result
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>, PgnError> {
+        if let Some(token) = self.pending.take() {
+            return Ok(Some(token));
+        }
+        while let Some(c) = self.next_char()? {
+            let token = match c {
+                _ if c.is_whitespace() => continue,
+                '[' => self.read_tag()?,
+                '{' => Token::Comment(self.read_until('}')?.trim().to_string()),
+                ';' => Token::Comment(self.read_rest_of_line().trim().to_string()),
+                '(' => Token::VariationStart,
+                ')' => Token::VariationEnd,
+                '$' => {
+                    let symbol = self.read_symbol(c);
+                    Token::Nag(symbol[1..].parse().map_err(|_| PgnError::UnexpectedToken(symbol.clone()))?)
+                }
+                _ => {
+                    let symbol = self.read_symbol(c);
+                    if let Some(result) = PgnResult::parse(&symbol) {
+                        Token::Result(result)
+                    } else {
+                        // Move numbers can be glued to the move, as in 1.e4 or 12...Nf6
+                        let san = match symbol.rfind('.') {
+                            Some(index) => &symbol[index + 1..],
+                            None if symbol.chars().all(|c| c.is_ascii_digit()) => "",
+                            None => &symbol,
+                        };
+                        if san.is_empty() {
+                            continue;
+                        }
+                        Token::Move(san.to_string())
+                    }
+                }
+            };
+            return Ok(Some(token));
+        }
+        Ok(None)
+    }
+
+    fn read_game(&mut self) -> Result<Option<PgnGame>, PgnError> {
+        let mut game = PgnGame::new();
+        let mut token = self.next_token()?;
+        while let Some(Token::Tag(name, value)) = token {
+            game.tags.push((name, value));
+            token = self.next_token()?;
+        }
+        match token {
+            None if game.tags.is_empty() => return Ok(None),
+            None => return Ok(Some(game)),
+            Some(token) => self.pending = Some(token),
+        }
+
+        let mut board = game.starting_board()?;
+        game.moves = self.read_moves(&mut board, 0)?;
+        if let Some(Token::Result(result)) = self.pending {
+            game.result = result;
+            self.pending = None;
+        }
+        Ok(Some(game))
+    }
+
+    // Stops at the result, at a variation end or at the tags of the next game, leaving that token pending
+    fn read_moves(&mut self, board: &mut Board, depth: usize) -> Result<Vec<PgnMove>, PgnError> {
+        let mut moves: Vec<PgnMove> = Vec::new();
+        let mut comment_before = None;
+        while let Some(token) = self.next_token()? {
+            match token {
+                Token::Move(symbol) => {
+                    let mut san = symbol.as_str();
+                    let mut nags = Vec::new();
+                    for (suffix, nag) in PgnReader::<R>::SUFFIX_NAGS.iter() {
+                        if san.len() > suffix.len() && san.ends_with(suffix) {
+                            san = &san[..san.len() - suffix.len()];
+                            nags.push(*nag);
+                            break;
+                        }
+                    }
+                    let board_move = board.parse_san(san, &mut self.move_list, &mut self.attack_info)
+                        .map_err(|error| PgnError::InvalidMove(board.move_number / 2 + 1, error))?;
+                    board.do_move(&board_move);
+                    let mut pgn_move = PgnMove::new(board_move);
+                    pgn_move.nags = nags;
+                    pgn_move.comment_before = comment_before.take();
+                    moves.push(pgn_move);
+                }
+                Token::Nag(nag) => match moves.last_mut() {
+                    Some(last) => last.nags.push(nag),
+                    None => return Err(PgnError::UnexpectedToken(format!("${}", nag))),
+                },
+                Token::Comment(comment) => match moves.last_mut() {
+                    Some(last) if comment_before.is_none() => {
+                        last.comment = Some(match last.comment.take() {
+                            Some(previous) => format!("{} {}", previous, comment),
+                            None => comment,
+                        })
+                    }
+                    _ => comment_before = Some(comment),
+                },
+                Token::VariationStart => {
+                    let last = moves.last_mut().ok_or(PgnError::UnbalancedVariation)?;
+                    let mut variation_board = board.clone();
+                    variation_board.undo_move(&last.board_move);
+                    let variation = self.read_moves(&mut variation_board, depth + 1)?;
+                    if self.pending.take() != Some(Token::VariationEnd) {
+                        return Err(PgnError::UnbalancedVariation);
+                    }
+                    last.variations.push(variation);
+                }
+                Token::VariationEnd if depth == 0 => return Err(PgnError::UnbalancedVariation),
+                Token::Result(_) | Token::Tag(_, _) if depth > 0 => return Err(PgnError::UnbalancedVariation),
+                _ => {
+                    self.pending = Some(token);
+                    break;
+                }
+            }
+        }
+        Ok(moves)
+    }
+
+    // Drops what is left of a broken game
+    fn skip_game(&mut self) -> Result<(), PgnError> {
+        while let Some(token) = self.next_token()? {
+            match token {
+                Token::Result(_) => break,
+                Token::Tag(_, _) => {
+                    self.pending = Some(token);
+                    break;
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = Result<PgnGame, PgnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.read_game() {
+            Ok(game) => game.map(Ok),
+            Err(error) => {
+                if let Err(io_error @ PgnError::Io(_)) = self.skip_game() {
+                    return Some(Err(io_error));
+                }
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::board_move::BoardMove;
+    use crate::types::square::Square;
+
+    fn read(pgn: &str) -> Vec<Result<PgnGame, PgnError>> {
+        PgnReader::new(pgn.as_bytes()).collect()
+    }
+
+    #[test]
+    fn tags_and_moves() {
+        let pgn = "[Event \"Test \\\"quoted\\\"\"]\n[White \"A\"]\n[Result \"1-0\"]\n\n1. e4 e5 2.Nf3 Nc6 3. Bb5 a6 1-0\n";
+        let games = read(pgn);
+        assert_eq!(games.len(), 1);
+        let game = games[0].as_ref().unwrap();
+        assert_eq!(game.tag("Event"), Some("Test \"quoted\""));
+        assert_eq!(game.tag("White"), Some("A"));
+        assert_eq!(game.result, PgnResult::WhiteWins);
+        assert_eq!(game.moves.len(), 6);
+        assert_eq!(game.moves[4].board_move, BoardMove::build_normal(&Square::F1, &Square::B5));
+    }
+
+    #[test]
+    fn comments_nags_and_variations() {
+        let pgn = "{Opening} 1. e4 $1 {Best by test} (1. d4 d5 (1... Nf6 2. c4) 2. c4) 1... c5!? ; Sicilian\n2. Nf3 *";
+        let game = read(pgn).remove(0).unwrap();
+        assert_eq!(game.result, PgnResult::Unknown);
+        assert_eq!(game.moves.len(), 3);
+        assert_eq!(game.moves[0].comment_before, Some("Opening".to_string()));
+        assert_eq!(game.moves[0].nags, vec![1]);
+        assert_eq!(game.moves[0].comment, Some("Best by test".to_string()));
+        assert_eq!(game.moves[0].variations.len(), 1);
+        let variation = &game.moves[0].variations[0];
+        assert_eq!(variation.len(), 3);
+        assert_eq!(variation[0].board_move, BoardMove::build_normal(&Square::D2, &Square::D4));
+        assert_eq!(variation[1].variations[0][0].board_move, BoardMove::build_normal(&Square::G8, &Square::F6));
+        assert_eq!(game.moves[1].nags, vec![5]);
+        assert_eq!(game.moves[1].comment, Some("Sicilian".to_string()));
+    }
+
+    #[test]
+    fn fen_start() {
+        let pgn = "[SetUp \"1\"]\n[FEN \"6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\"]\n\n1. Ra8# 1-0\n\n[Event \"Next\"]\n\n1. d4 0-1";
+        let games = read(pgn);
+        assert_eq!(games.len(), 2);
+        let game = games[0].as_ref().unwrap();
+        assert_eq!(game.moves[0].board_move, BoardMove::build_normal(&Square::A1, &Square::A8));
+        assert_eq!(games[1].as_ref().unwrap().result, PgnResult::BlackWins);
+    }
+
+    #[test]
+    fn castling_and_escapes() {
+        let pgn = "% skipped line\n1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4. 0-0 Nf6 5. d3 O-O 1/2-1/2";
+        let game = read(pgn).remove(0).unwrap();
+        assert_eq!(game.moves[6].board_move, BoardMove::build_castling(&Square::E1, &Square::H1));
+        assert_eq!(game.moves[9].board_move, BoardMove::build_castling(&Square::E8, &Square::H8));
+        assert_eq!(game.result, PgnResult::Draw);
+    }
+
+    #[test]
+    fn missing_result() {
+        let games = read("1. e4 e5\n\n[Event \"Second\"]\n1. d4 *");
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].as_ref().unwrap().moves.len(), 2);
+        assert_eq!(games[1].as_ref().unwrap().tag("Event"), Some("Second"));
+    }
+
+    #[test]
+    fn errors_skip_game() {
+        let games = read("1. e4 e4 2. d4 1-0\n[Event \"Next\"]\n1. e4 (1... d5) *\n1. d4 ) *\n1. e4 *");
+        assert_eq!(games.len(), 4);
+        match &games[0] {
+            Err(PgnError::InvalidMove(1, _)) => {}
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(games[1].is_err());
+        assert!(games[2].is_err());
+        assert_eq!(games[3].as_ref().unwrap().moves.len(), 1);
+    }
+}
@@ -0,0 +1,173 @@
+use std::io::Write;
+
+use crate::advanced::attack_info::AttackInfo;
+use crate::advanced::board::Board;
+use crate::advanced::move_list::MoveList;
+use crate::pgn::pgn_game::{PgnError, PgnGame, PgnMove};
+use crate::types::color::Color;
+
+pub struct PgnWriter<W: Write> {
+    output: W,
+    move_list: MoveList,
+    attack_info: AttackInfo,
+}
+
+impl<W: Write> PgnWriter<W> {
+    pub const LINE_WIDTH: usize = 80;
+    // Seven tag roster, always exported first and in this order
+    pub const REQUIRED_TAGS: [(&'static str, &'static str); 7] = [
+        ("Event", "?"),
+        ("Site", "?"),
+        ("Date", "????.??.??"),
+        ("Round", "?"),
+        ("White", "?"),
+        ("Black", "?"),
+        (PgnGame::TAG_RESULT, "*"),
+    ];
+
+    pub fn new(output: W) -> Self {
+        PgnWriter {
+            output,
+            move_list: MoveList::new(),
+            attack_info: AttackInfo::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.output
+    }
+
+    pub fn write_game(&mut self, game: &PgnGame) -> Result<(), PgnError> {
+        for (name, default) in PgnWriter::<W>::REQUIRED_TAGS.iter() {
+            let value = if *name == PgnGame::TAG_RESULT {
+                game.result.to_str()
+            } else {
+                game.tag(name).unwrap_or(default)
+            };
+            self.write_tag(name, value)?;
+        }
+        for (name, value) in game.tags.iter() {
+            if !PgnWriter::<W>::REQUIRED_TAGS.iter().any(|(required, _)| required == name) {
+                self.write_tag(name, value)?;
+            }
+        }
+        writeln!(self.output)?;
+
+        let mut tokens = Vec::new();
+        let mut board = game.starting_board()?;
+        self.push_moves(&mut tokens, &mut board, &game.moves);
+        tokens.push(game.result.to_str().to_string());
+
+        let mut line = String::new();
+        for token in tokens.iter() {
+            if !line.is_empty() && line.len() + token.len() + 1 > PgnWriter::<W>::LINE_WIDTH {
+                writeln!(self.output, "{}", line)?;
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(token);
+        }
+        writeln!(self.output, "{}", line)?;
+        writeln!(self.output)?;
+        Ok(())
+    }
+
+    fn write_tag(&mut self, name: &str, value: &str) -> Result<(), PgnError> {
+        let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+        writeln!(self.output, "[{} \"{}\"]", name, value)?;
+        Ok(())
+    }
+
+    // Black moves need their number at the start of a line or after a comment or variation
+    fn push_moves(&mut self, tokens: &mut Vec<String>, board: &mut Board, moves: &[PgnMove]) {
+        let mut needs_number = true;
+        for pgn_move in moves.iter() {
+            if let Some(comment) = &pgn_move.comment_before {
+                tokens.push(PgnWriter::<W>::comment(comment));
+                needs_number = true;
+            }
+            let move_number = board.move_number / 2 + 1;
+            if board.color_to_move == Color::White {
+                tokens.push(format!("{}.", move_number));
+            } else if needs_number {
+                tokens.push(format!("{}...", move_number));
+            }
+            tokens.push(board.move_to_san(&pgn_move.board_move, &mut self.move_list, &mut self.attack_info));
+            needs_number = false;
+
+            for nag in pgn_move.nags.iter() {
+                tokens.push(format!("${}", nag));
+            }
+            if let Some(comment) = &pgn_move.comment {
+                tokens.push(PgnWriter::<W>::comment(comment));
+                needs_number = true;
+            }
+            for variation in pgn_move.variations.iter().filter(|variation| !variation.is_empty()) {
+                let start = tokens.len();
+                self.push_moves(tokens, &mut board.clone_position(), variation);
+                tokens[start].insert(0, '(');
+                tokens.last_mut().unwrap().push(')');
+                needs_number = true;
+            }
+            board.do_move(&pgn_move.board_move);
+        }
+    }
+
+    // Braces can not be escaped inside a comment
+    fn comment(text: &str) -> String {
+        format!("{{{}}}", text.replace('}', ""))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::advanced::board::Board;
+    use crate::pgn::pgn_game::PgnResult;
+    use crate::pgn::pgn_reader::PgnReader;
+    use crate::types::board_move::BoardMove;
+    use crate::types::square::Square;
+
+    fn write(game: &PgnGame) -> String {
+        let mut writer = PgnWriter::new(Vec::new());
+        writer.write_game(game).unwrap();
+        String::from_utf8(writer.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn write_moves() {
+        let moves = [
+            BoardMove::build_normal(&Square::E2, &Square::E4),
+            BoardMove::build_normal(&Square::E7, &Square::E5),
+            BoardMove::build_normal(&Square::G1, &Square::F3),
+        ];
+        let mut game = PgnGame::from_moves(&Board::default(), &moves);
+        game.set_tag("White", "Urubu \"dev\"");
+        game.set_tag("ECO", "C40");
+        game.set_result(PgnResult::WhiteWins);
+        assert_eq!(write(&game), "[Event \"?\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n[Round \"?\"]\n\
+            [White \"Urubu \\\"dev\\\"\"]\n[Black \"?\"]\n[Result \"1-0\"]\n[ECO \"C40\"]\n\n1. e4 e5 2. Nf3 1-0\n\n");
+    }
+
+    #[test]
+    fn write_black_first() {
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 b - - 0 12");
+        let game = PgnGame::from_moves(&board, &[BoardMove::build_normal(&Square::E8, &Square::D7)]);
+        let pgn = write(&game);
+        assert!(pgn.contains("[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/8/R3K3 b - - 0 12\"]\n"));
+        assert!(pgn.ends_with("\n12... Kd7 *\n\n"));
+    }
+
+    #[test]
+    fn round_trip() {
+        let pgn = "[Event \"?\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n[Round \"?\"]\n[White \"?\"]\n[Black \"?\"]\n\
+            [Result \"*\"]\n\n{Opening} 1. e4 $1 {Best by test} (1. d4 d5 (1... Nf6 2. c4) 2. c4) 1... c5 $5\n\
+            2. Nf3 d6 3. d4 cxd4 4. Nxd4 Nf6 5. Nc3 a6 6. Be3 e5 7. Nb3 Be6 8. f3 Be7 9. Qd2\n\
+            O-O 10. O-O-O Nbd7 11. g4 b5 12. g5 b4 13. Ne2 Ne8 14. f4 a5 15. f5 a4 16. Nbd4\n\
+            exd4 17. Nxd4 b3 18. Kb1 bxc2+ 19. Nxc2 Bb3 20. axb3 axb3 21. Na3 Ra4 *\n\n";
+        let game = PgnReader::new(pgn.as_bytes()).next().unwrap().unwrap();
+        assert_eq!(write(&game), pgn);
+    }
+}
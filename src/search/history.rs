@@ -0,0 +1,157 @@
+use crate::advanced::board::Board;
+use crate::advanced::move_list::MoveList;
+use crate::types::board_move::BoardMove;
+use crate::types::color::Color;
+use crate::types::piece_type::PieceType;
+use crate::types::square::Square;
+
+// Statistics about quiet moves causing cutoffs, used to order the moves of later nodes
+pub struct History {
+    killers: [[BoardMove; History::KILLERS]; MoveList::MAX_PLIES],
+    // Indexed by the continuation key of the previous move
+    counter_moves: Vec<BoardMove>,
+    // Indexed by color, square from and square to
+    butterfly: Vec<i16>,
+    // Indexed by the continuation key of an earlier move and the one of the move
+    continuation: Vec<i16>,
+}
+
+impl History {
+    pub const KILLERS: usize = 2;
+    // Piece of each color and its square to, for a move that was played or will be
+    pub const CONTINUATION_KEYS: usize = Color::NUM_COLORS * PieceType::NUM_PIECE_TYPES * Square::NUM_SQUARES;
+    // Null moves and the root have nothing to follow up on
+    pub const NO_CONTINUATION: usize = History::CONTINUATION_KEYS;
+
+    const MAX_HISTORY: i32 = 16384;
+    const MAX_BONUS: i32 = 1600;
+
+    pub fn new() -> Self {
+        History {
+            killers: [[BoardMove::NONE; History::KILLERS]; MoveList::MAX_PLIES],
+            counter_moves: vec![BoardMove::NONE; History::CONTINUATION_KEYS + 1],
+            butterfly: vec![0; Color::NUM_COLORS * Square::NUM_SQUARES * Square::NUM_SQUARES],
+            continuation: vec![0; (History::CONTINUATION_KEYS + 1) * History::CONTINUATION_KEYS],
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = History::new();
+    }
+
+    // Killers only make sense inside the tree they were found in
+    pub fn new_search(&mut self) {
+        self.killers = [[BoardMove::NONE; History::KILLERS]; MoveList::MAX_PLIES];
+    }
+
+    // Computed before the move is played
+    #[inline]
+    pub fn continuation_key(board: &Board, board_move: &BoardMove) -> usize {
+        let piece = board.color_to_move.to_usize() * PieceType::NUM_PIECE_TYPES +
+            board.piece_type(&board_move.square_from()).to_usize();
+        piece * Square::NUM_SQUARES + board_move.square_to().to_usize()
+    }
+
+    #[inline]
+    pub fn killers(&self, ply: usize) -> [BoardMove; History::KILLERS] {
+        self.killers[ply]
+    }
+
+    #[inline]
+    pub fn counter_move(&self, previous_key: usize) -> BoardMove {
+        self.counter_moves[previous_key]
+    }
+
+    // Butterfly history plus the continuation history of the last two moves
+    #[inline]
+    pub fn quiet_score(&self, board: &Board, board_move: &BoardMove, continuations: &[usize; 2]) -> i32 {
+        let key = History::continuation_key(board, board_move);
+        self.butterfly[History::butterfly_index(&board.color_to_move, board_move)] as i32 +
+            continuations.iter()
+                .map(|continuation| self.continuation[continuation * History::CONTINUATION_KEYS + key] as i32)
+                .sum::<i32>()
+    }
+
+    // The quiet move caused a cutoff, the quiet moves searched before it did not
+    pub fn update_quiets(&mut self, board: &Board, ply: usize, continuations: &[usize; 2],
+                         best_move: &BoardMove, quiets_searched: &[BoardMove], depth: u8) {
+        if self.killers[ply][0] != *best_move {
+            self.killers[ply][1] = self.killers[ply][0];
+            self.killers[ply][0] = *best_move;
+        }
+        if continuations[0] != History::NO_CONTINUATION {
+            self.counter_moves[continuations[0]] = *best_move;
+        }
+
+        let bonus = (depth as i32 * depth as i32).min(History::MAX_BONUS);
+        self.update_quiet(board, continuations, best_move, bonus);
+        for board_move in quiets_searched.iter().filter(|board_move| *board_move != best_move) {
+            self.update_quiet(board, continuations, board_move, -bonus);
+        }
+    }
+
+    #[inline]
+    fn update_quiet(&mut self, board: &Board, continuations: &[usize; 2], board_move: &BoardMove, bonus: i32) {
+        let key = History::continuation_key(board, board_move);
+        History::apply_bonus(&mut self.butterfly[History::butterfly_index(&board.color_to_move, board_move)], bonus);
+        for continuation in continuations.iter().filter(|continuation| **continuation != History::NO_CONTINUATION) {
+            History::apply_bonus(&mut self.continuation[continuation * History::CONTINUATION_KEYS + key], bonus);
+        }
+    }
+
+    // Entries close to the limit move less, so they stay within it
+    #[inline]
+    fn apply_bonus(entry: &mut i16, bonus: i32) {
+        let value = *entry as i32;
+        *entry = (value + bonus - value * bonus.abs() / History::MAX_HISTORY) as i16;
+    }
+
+    #[inline]
+    fn butterfly_index(color: &Color, board_move: &BoardMove) -> usize {
+        (color.to_usize() * Square::NUM_SQUARES + board_move.square_from().to_usize()) * Square::NUM_SQUARES +
+            board_move.square_to().to_usize()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn update_quiets() {
+        let board = Board::default();
+        let mut history = History::new();
+        let e4 = BoardMove::build_normal(&Square::E2, &Square::E4);
+        let d4 = BoardMove::build_normal(&Square::D2, &Square::D4);
+        let nf3 = BoardMove::build_normal(&Square::G1, &Square::F3);
+        let previous = History::continuation_key(&board, &nf3);
+        let continuations = [previous, History::NO_CONTINUATION];
+
+        history.update_quiets(&board, 3, &continuations, &e4, &[d4, e4], 4);
+        assert_eq!(history.killers(3), [e4, BoardMove::NONE]);
+        assert_eq!(history.killers(2), [BoardMove::NONE; History::KILLERS]);
+        assert_eq!(history.counter_move(previous), e4);
+        assert_eq!(history.quiet_score(&board, &e4, &continuations), 32);
+        assert_eq!(history.quiet_score(&board, &d4, &continuations), -32);
+        assert_eq!(history.quiet_score(&board, &e4, &[History::NO_CONTINUATION; 2]), 16);
+
+        history.update_quiets(&board, 3, &continuations, &d4, &[d4], 4);
+        history.update_quiets(&board, 3, &continuations, &d4, &[d4], 4);
+        assert_eq!(history.killers(3), [d4, e4]);
+        history.new_search();
+        assert_eq!(history.killers(3), [BoardMove::NONE; History::KILLERS]);
+        assert_eq!(history.counter_move(previous), d4);
+    }
+
+    #[test]
+    fn bounded_scores() {
+        let board = Board::default();
+        let mut history = History::new();
+        let e4 = BoardMove::build_normal(&Square::E2, &Square::E4);
+        for _ in 0..1000 {
+            history.update_quiets(&board, 0, &[History::NO_CONTINUATION; 2], &e4, &[e4], 60);
+        }
+        let score = history.quiet_score(&board, &e4, &[History::NO_CONTINUATION; 2]);
+        assert!(score > 0 && score <= History::MAX_HISTORY);
+    }
+}
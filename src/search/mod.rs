@@ -0,0 +1,8 @@
+pub mod history;
+pub mod move_picker;
+pub mod principal_variation;
+pub mod score;
+pub mod search_params;
+pub mod searcher;
+pub mod time_manager;
+pub mod transposition_table;
@@ -0,0 +1,217 @@
+use crate::advanced::attack_info::AttackInfo;
+use crate::advanced::board::Board;
+use crate::advanced::move_list::MoveList;
+use crate::advanced::see::SEE_VALUE;
+use crate::search::history::History;
+use crate::types::board_move::BoardMove;
+use crate::types::piece_type::PieceType;
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+enum Stage {
+    Generate,
+    GoodNoisy,
+    Quiets,
+    Remaining,
+    Done,
+}
+
+// Returns the moves of a node from the most to the least promising one, quiets are only
+// generated once the winning captures are exhausted
+pub struct MovePicker {
+    stage: Stage,
+    hash_move: BoardMove,
+    killers: [BoardMove; History::KILLERS],
+    counter_move: BoardMove,
+    continuations: [usize; 2],
+    generate_quiets: bool,
+}
+
+impl MovePicker {
+    const HASH_SCORE: u64 = u64::MAX;
+    const GOOD_NOISY_SCORE: u64 = 1 << 40;
+    const KILLER_SCORE: u64 = 1 << 39;
+    // History is added to it, so it can go both ways
+    const QUIET_SCORE: u64 = 1 << 32;
+    // Losing captures and under promotions
+    const BAD_NOISY_SCORE: u64 = 0;
+
+    pub fn new(hash_move: BoardMove, history: &History, ply: usize, continuations: [usize; 2]) -> Self {
+        MovePicker {
+            stage: Stage::Generate,
+            hash_move,
+            killers: history.killers(ply),
+            counter_move: history.counter_move(continuations[0]),
+            continuations,
+            generate_quiets: true,
+        }
+    }
+
+    // Captures and promotions only
+    pub fn noisy(hash_move: BoardMove) -> Self {
+        MovePicker {
+            stage: Stage::Generate,
+            hash_move,
+            killers: [BoardMove::NONE; History::KILLERS],
+            counter_move: BoardMove::NONE,
+            continuations: [History::NO_CONTINUATION; 2],
+            generate_quiets: false,
+        }
+    }
+
+    pub fn next(&mut self, board: &Board, move_list: &mut MoveList, attack_info: &mut AttackInfo,
+                history: &History) -> Option<BoardMove> {
+        loop {
+            match self.stage {
+                Stage::Generate => {
+                    let start_index = move_list.generated_index();
+                    move_list.generate_noisy(board, attack_info);
+                    move_list.score_moves(start_index, |board_move| self.noisy_score(board, board_move));
+                    self.stage = Stage::GoodNoisy;
+                    // A quiet hash move should not wait for the captures
+                    if self.generate_quiets && self.hash_move != BoardMove::NONE && is_quiet(board, &self.hash_move) {
+                        self.stage = Stage::Quiets;
+                    }
+                }
+                Stage::GoodNoisy => {
+                    if let Some(board_move) = move_list.next_above(MovePicker::GOOD_NOISY_SCORE) {
+                        return Some(board_move);
+                    }
+                    self.stage = if self.generate_quiets {
+                        Stage::Quiets
+                    } else {
+                        Stage::Remaining
+                    };
+                }
+                Stage::Quiets => {
+                    let start_index = move_list.generated_index();
+                    move_list.generate_quiets(board, attack_info);
+                    move_list.score_moves(start_index, |board_move| self.quiet_score(board, board_move, history));
+                    self.stage = Stage::Remaining;
+                }
+                Stage::Remaining => {
+                    if move_list.has_next() {
+                        return Some(move_list.next());
+                    }
+                    self.stage = Stage::Done;
+                }
+                Stage::Done => return None,
+            }
+        }
+    }
+
+    // Most valuable victim first, then least valuable attacker
+    #[inline]
+    fn noisy_score(&self, board: &Board, board_move: &BoardMove) -> u64 {
+        if *board_move == self.hash_move {
+            return MovePicker::HASH_SCORE;
+        }
+        let move_type = board_move.move_type();
+        let victim = if move_type.is_passant() {
+            PieceType::PAWN
+        } else {
+            board.piece_type(&board_move.square_to())
+        };
+        let promoted_piece_type = move_type.promoted_piece_type();
+        let attacker = board.piece_type(&board_move.square_from());
+        let mvv_lva = (SEE_VALUE[victim.to_usize()] + SEE_VALUE[promoted_piece_type.to_usize()]) as u64 *
+            PieceType::NUM_PIECE_TYPES as u64 + (PieceType::KING.to_usize() - attacker.to_usize()) as u64;
+
+        let good = if move_type.is_promotion() && promoted_piece_type != PieceType::QUEEN {
+            false
+        } else {
+            victim == PieceType::NONE || board.see_ge(board_move, 0)
+        };
+        if good {
+            MovePicker::GOOD_NOISY_SCORE + mvv_lva
+        } else {
+            MovePicker::BAD_NOISY_SCORE + mvv_lva
+        }
+    }
+
+    #[inline]
+    fn quiet_score(&self, board: &Board, board_move: &BoardMove, history: &History) -> u64 {
+        if *board_move == self.hash_move {
+            MovePicker::HASH_SCORE
+        } else if *board_move == self.killers[0] {
+            MovePicker::KILLER_SCORE + 2
+        } else if *board_move == self.killers[1] {
+            MovePicker::KILLER_SCORE + 1
+        } else if *board_move == self.counter_move {
+            MovePicker::KILLER_SCORE
+        } else {
+            (MovePicker::QUIET_SCORE as i64 + history.quiet_score(board, board_move, &self.continuations) as i64) as u64
+        }
+    }
+}
+
+// Neither a capture nor a promotion, castling takes our own rook
+#[inline]
+pub fn is_quiet(board: &Board, board_move: &BoardMove) -> bool {
+    let move_type = board_move.move_type();
+    move_type.is_castling() ||
+        !move_type.is_promotion() && !move_type.is_passant() && board.piece_type(&board_move.square_to()) == PieceType::NONE
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::move_type::MoveType;
+    use crate::types::square::Square;
+
+    fn pick_all(board: &Board, picker: &mut MovePicker, history: &History) -> Vec<BoardMove> {
+        let mut move_list = MoveList::new();
+        let mut attack_info = AttackInfo::new();
+        let mut result = Vec::new();
+        move_list.start_ply();
+        while let Some(board_move) = picker.next(board, &mut move_list, &mut attack_info, history) {
+            result.push(board_move);
+        }
+        move_list.end_ply();
+        result
+    }
+
+    #[test]
+    fn noisy_order() {
+        // The pawn takes the defended rook first, the queen taking it loses material
+        let board = Board::from_fen("4k3/1P6/4p3/3r4/2P5/8/3Q4/4K3 w - - 0 1");
+        let history = History::new();
+        let moves = pick_all(&board, &mut MovePicker::noisy(BoardMove::NONE), &history);
+        assert_eq!(moves[..4], [
+            BoardMove::build_move(&Square::B7, &Square::B8, &MoveType::PROMOTION_QUEEN),
+            BoardMove::build_normal(&Square::C4, &Square::D5),
+            BoardMove::build_move(&Square::B7, &Square::B8, &MoveType::PROMOTION_ROOK),
+            BoardMove::build_normal(&Square::D2, &Square::D5),
+        ]);
+        assert_eq!(moves.len(), 6);
+    }
+
+    #[test]
+    fn staged_order() {
+        let board = Board::from_fen("4k3/8/8/3r4/2P5/8/8/4K1N1 w - - 0 1");
+        let mut history = History::new();
+        let e2 = BoardMove::build_normal(&Square::E1, &Square::E2);
+        let f3 = BoardMove::build_normal(&Square::G1, &Square::F3);
+        let h3 = BoardMove::build_normal(&Square::G1, &Square::H3);
+        let capture = BoardMove::build_normal(&Square::C4, &Square::D5);
+        history.update_quiets(&board, 1, &[History::NO_CONTINUATION; 2], &e2, &[e2], 3);
+        history.update_quiets(&board, 2, &[History::NO_CONTINUATION; 2], &h3, &[f3, h3], 3);
+
+        let moves = pick_all(&board, &mut MovePicker::new(BoardMove::NONE, &history, 2, [History::NO_CONTINUATION; 2]), &history);
+        assert_eq!(moves[..3], [capture, h3, e2]);
+        assert_eq!(*moves.last().unwrap(), f3);
+        assert_eq!(moves.len(), board.legal_moves(&mut MoveList::new(), &mut AttackInfo::new()).len());
+
+        let moves = pick_all(&board, &mut MovePicker::new(f3, &history, 2, [History::NO_CONTINUATION; 2]), &history);
+        assert_eq!(moves[..2], [f3, capture]);
+        assert_eq!(moves.len(), board.legal_moves(&mut MoveList::new(), &mut AttackInfo::new()).len());
+    }
+
+    #[test]
+    fn quiet_moves() {
+        let board = Board::from_fen("r3k3/8/8/8/8/8/4p3/R3K3 w Q - 0 1");
+        assert!(is_quiet(&board, &BoardMove::build_castling(&Square::E1, &Square::A1)));
+        assert!(is_quiet(&board, &BoardMove::build_normal(&Square::A1, &Square::A7)));
+        assert!(!is_quiet(&board, &BoardMove::build_normal(&Square::A1, &Square::A8)));
+        assert!(!is_quiet(&board, &BoardMove::build_normal(&Square::E1, &Square::E2)));
+    }
+}
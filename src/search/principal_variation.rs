@@ -0,0 +1,75 @@
+use crate::advanced::move_list::MoveList;
+use crate::types::board_move::BoardMove;
+
+// Triangular table, each ply keeps the best line found from it
+pub struct PrincipalVariation {
+    moves: [[BoardMove; MoveList::MAX_PLIES]; MoveList::MAX_PLIES],
+    length: [usize; MoveList::MAX_PLIES],
+}
+
+impl PrincipalVariation {
+    pub fn new() -> Self {
+        PrincipalVariation {
+            moves: [[BoardMove::NONE; MoveList::MAX_PLIES]; MoveList::MAX_PLIES],
+            length: [0; MoveList::MAX_PLIES],
+        }
+    }
+
+    #[inline]
+    pub fn clear(&mut self, ply: usize) {
+        self.length[ply] = 0;
+    }
+
+    #[inline]
+    pub fn update(&mut self, ply: usize, board_move: BoardMove) {
+        self.moves[ply][0] = board_move;
+        let child_length = if ply + 1 < MoveList::MAX_PLIES {
+            self.length[ply + 1].min(MoveList::MAX_PLIES - 1)
+        } else {
+            0
+        };
+        for index in 0..child_length {
+            self.moves[ply][index + 1] = self.moves[ply + 1][index];
+        }
+        self.length[ply] = child_length + 1;
+    }
+
+    #[inline]
+    pub fn best_move(&self) -> BoardMove {
+        if self.length[0] > 0 {
+            self.moves[0][0]
+        } else {
+            BoardMove::NONE
+        }
+    }
+
+    pub fn line(&self, ply: usize) -> Vec<BoardMove> {
+        self.moves[ply][..self.length[ply]].to_vec()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::square::Square;
+
+    #[test]
+    fn update_collects_child_line() {
+        let mut principal_variation = PrincipalVariation::new();
+        let move1 = BoardMove::build_normal(&Square::E2, &Square::E4);
+        let move2 = BoardMove::build_normal(&Square::E7, &Square::E5);
+        principal_variation.clear(2);
+        principal_variation.update(1, move2);
+        principal_variation.update(0, move1);
+        assert_eq!(principal_variation.line(0), vec![move1, move2]);
+        assert_eq!(principal_variation.best_move(), move1);
+    }
+
+    #[test]
+    fn clear() {
+        let mut principal_variation = PrincipalVariation::new();
+        principal_variation.clear(0);
+        assert_eq!(principal_variation.best_move(), BoardMove::NONE);
+        assert!(principal_variation.line(0).is_empty());
+    }
+}
@@ -0,0 +1,48 @@
+use crate::advanced::move_list::MoveList;
+
+pub const DRAW: i32 = 0;
+pub const INFINITE: i32 = 32_000;
+pub const MATE: i32 = 31_000;
+// Any score beyond this bound is a mate found inside the search tree
+pub const MATE_BOUND: i32 = MATE - MoveList::MAX_PLIES as i32;
+
+#[inline]
+pub fn mate_in(ply: usize) -> i32 {
+    MATE - ply as i32
+}
+
+#[inline]
+pub fn mated_in(ply: usize) -> i32 {
+    -MATE + ply as i32
+}
+
+#[inline]
+pub fn is_mate(score: i32) -> bool {
+    score.abs() >= MATE_BOUND
+}
+
+// Number of moves to mate, negative when we are getting mated
+#[inline]
+pub fn mate_moves(score: i32) -> i32 {
+    if score > 0 {
+        (MATE - score + 1) / 2
+    } else {
+        -(MATE + score) / 2
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn mate_scores() {
+        assert!(is_mate(mate_in(1)));
+        assert!(is_mate(mated_in(2)));
+        assert!(!is_mate(900));
+        assert_eq!(mate_moves(mate_in(1)), 1);
+        assert_eq!(mate_moves(mate_in(3)), 2);
+        assert_eq!(mate_moves(mated_in(0)), 0);
+        assert_eq!(mate_moves(mated_in(2)), -1);
+    }
+}
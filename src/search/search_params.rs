@@ -0,0 +1,221 @@
+// Selectivity of the search, every technique can be turned off and tuned by name
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct SearchParams {
+    pub null_move: bool,
+    pub null_move_min_depth: i32,
+    pub null_move_reduction: i32,
+    // Depth divided by it is added to the reduction
+    pub null_move_depth_divisor: i32,
+    // Null move cutoffs are verified when the side to move has at most these pieces besides pawns and king
+    pub null_move_verification_pieces: i32,
+    pub reverse_futility: bool,
+    pub reverse_futility_depth: i32,
+    pub reverse_futility_margin: i32,
+    pub razoring: bool,
+    pub razoring_depth: i32,
+    pub razoring_margin: i32,
+    pub futility: bool,
+    pub futility_depth: i32,
+    pub futility_base: i32,
+    pub futility_margin: i32,
+    pub late_move_pruning: bool,
+    pub late_move_pruning_depth: i32,
+    // Quiet moves searched before the rest is pruned, depth squared is added to it
+    pub late_move_pruning_base: i32,
+    pub late_move_reductions: bool,
+    pub late_move_reduction_depth: i32,
+    pub late_move_reduction_moves: i32,
+    // Reduction is base + ln(depth) * ln(moves) / divisor, both in hundredths
+    pub late_move_reduction_base: i32,
+    pub late_move_reduction_divisor: i32,
+    pub check_extension: bool,
+    // The hash move is extended when every other move fails low against its score minus the margin
+    pub singular_extension: bool,
+    pub singular_depth: i32,
+    pub singular_margin: i32,
+    pub recapture_extension: bool,
+    // Pushes to the sixth or seventh rank of pawns no enemy pawn can stop
+    pub passed_pawn_extension: bool,
+    // Extensions allowed in a root iteration, percentage of the nodes it searched
+    pub extension_budget: i32,
+}
+
+impl SearchParams {
+    pub const REDUCTION_TABLE_SIZE: usize = 64;
+
+    pub fn new() -> Self {
+        SearchParams {
+            null_move: true,
+            null_move_min_depth: 3,
+            null_move_reduction: 3,
+            null_move_depth_divisor: 4,
+            null_move_verification_pieces: 2,
+            reverse_futility: true,
+            reverse_futility_depth: 7,
+            reverse_futility_margin: 90,
+            razoring: true,
+            razoring_depth: 2,
+            razoring_margin: 250,
+            futility: true,
+            futility_depth: 6,
+            futility_base: 100,
+            futility_margin: 90,
+            late_move_pruning: true,
+            late_move_pruning_depth: 7,
+            late_move_pruning_base: 3,
+            late_move_reductions: true,
+            late_move_reduction_depth: 3,
+            late_move_reduction_moves: 3,
+            late_move_reduction_base: 75,
+            late_move_reduction_divisor: 225,
+            check_extension: true,
+            singular_extension: true,
+            singular_depth: 8,
+            singular_margin: 2,
+            recapture_extension: true,
+            passed_pawn_extension: true,
+            extension_budget: 20,
+        }
+    }
+
+    // Every technique off, a plain alpha beta search
+    pub fn disabled() -> Self {
+        SearchParams {
+            null_move: false,
+            reverse_futility: false,
+            razoring: false,
+            futility: false,
+            late_move_pruning: false,
+            late_move_reductions: false,
+            check_extension: false,
+            singular_extension: false,
+            recapture_extension: false,
+            passed_pawn_extension: false,
+            ..SearchParams::new()
+        }
+    }
+
+    fn switches(&mut self) -> [(&'static str, &mut bool); 10] {
+        [
+            ("NullMove", &mut self.null_move),
+            ("ReverseFutility", &mut self.reverse_futility),
+            ("Razoring", &mut self.razoring),
+            ("Futility", &mut self.futility),
+            ("LateMovePruning", &mut self.late_move_pruning),
+            ("LateMoveReductions", &mut self.late_move_reductions),
+            ("CheckExtension", &mut self.check_extension),
+            ("SingularExtension", &mut self.singular_extension),
+            ("RecaptureExtension", &mut self.recapture_extension),
+            ("PassedPawnExtension", &mut self.passed_pawn_extension),
+        ]
+    }
+
+    // Name, value, minimum and maximum
+    fn values(&mut self) -> [(&'static str, &mut i32, i32, i32); 20] {
+        [
+            ("NullMoveMinDepth", &mut self.null_move_min_depth, 1, 20),
+            ("NullMoveReduction", &mut self.null_move_reduction, 1, 10),
+            ("NullMoveDepthDivisor", &mut self.null_move_depth_divisor, 1, 20),
+            ("NullMoveVerificationPieces", &mut self.null_move_verification_pieces, 0, 16),
+            ("ReverseFutilityDepth", &mut self.reverse_futility_depth, 1, 20),
+            ("ReverseFutilityMargin", &mut self.reverse_futility_margin, 0, 1000),
+            ("RazoringDepth", &mut self.razoring_depth, 1, 10),
+            ("RazoringMargin", &mut self.razoring_margin, 0, 2000),
+            ("FutilityDepth", &mut self.futility_depth, 1, 20),
+            ("FutilityBase", &mut self.futility_base, 0, 1000),
+            ("FutilityMargin", &mut self.futility_margin, 0, 1000),
+            ("LateMovePruningDepth", &mut self.late_move_pruning_depth, 1, 20),
+            ("LateMovePruningBase", &mut self.late_move_pruning_base, 0, 100),
+            ("LateMoveReductionDepth", &mut self.late_move_reduction_depth, 1, 20),
+            ("LateMoveReductionMoves", &mut self.late_move_reduction_moves, 1, 64),
+            ("LateMoveReductionBase", &mut self.late_move_reduction_base, 0, 500),
+            ("LateMoveReductionDivisor", &mut self.late_move_reduction_divisor, 50, 1000),
+            ("SingularDepth", &mut self.singular_depth, 2, 30),
+            ("SingularMargin", &mut self.singular_margin, 0, 100),
+            ("ExtensionBudget", &mut self.extension_budget, 0, 100),
+        ]
+    }
+
+    // UCI option lines, one per parameter
+    pub fn uci_options(&self) -> Vec<String> {
+        let mut params = *self;
+        let mut result: Vec<String> = params.switches().iter()
+            .map(|(name, value)| format!("option name {} type check default {}", name, value))
+            .collect();
+        result.extend(params.values().iter()
+            .map(|(name, value, min, max)| format!("option name {} type spin default {} min {} max {}", name, value, min, max)));
+        result
+    }
+
+    // Names are not case sensitive, returns false for an unknown name
+    pub fn set(&mut self, name: &str, value: &str) -> Result<bool, String> {
+        for (switch_name, switch) in self.switches().iter_mut() {
+            if switch_name.eq_ignore_ascii_case(name) {
+                **switch = match value.to_lowercase().as_str() {
+                    "true" => true,
+                    "false" => false,
+                    _ => return Err(format!("invalid {} value {}", switch_name, value)),
+                };
+                return Ok(true);
+            }
+        }
+        for (value_name, param, min, max) in self.values().iter_mut() {
+            if value_name.eq_ignore_ascii_case(name) {
+                **param = match value.parse::<i32>() {
+                    Ok(value) if value >= *min && value <= *max => value,
+                    _ => return Err(format!("invalid {} value {}", value_name, value)),
+                };
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    // Late move reductions indexed by depth and number of moves searched
+    pub fn reduction_table(&self) -> [[u8; SearchParams::REDUCTION_TABLE_SIZE]; SearchParams::REDUCTION_TABLE_SIZE] {
+        let mut result = [[0; SearchParams::REDUCTION_TABLE_SIZE]; SearchParams::REDUCTION_TABLE_SIZE];
+        for (depth, reductions) in result.iter_mut().enumerate().skip(1) {
+            for (moves, reduction) in reductions.iter_mut().enumerate().skip(1) {
+                let value = self.late_move_reduction_base as f64 / 100.0 +
+                    (depth as f64).ln() * (moves as f64).ln() * 100.0 / self.late_move_reduction_divisor as f64;
+                *reduction = value.max(0.0) as u8;
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn set_by_name() {
+        let mut params = SearchParams::new();
+        assert_eq!(params.set("nullmove", "false"), Ok(true));
+        assert_eq!(params.set("FutilityMargin", "120"), Ok(true));
+        assert_eq!(params.set("Foo", "1"), Ok(false));
+        assert!(params.set("Razoring", "maybe").is_err());
+        assert!(params.set("RazoringDepth", "0").is_err());
+        assert!(!params.null_move);
+        assert_eq!(params.futility_margin, 120);
+        assert_eq!(params.razoring_depth, SearchParams::new().razoring_depth);
+    }
+
+    #[test]
+    fn uci_options() {
+        let options = SearchParams::new().uci_options();
+        assert_eq!(options.len(), 30);
+        assert_eq!(options[0], "option name NullMove type check default true");
+        assert!(options.contains(&"option name LateMoveReductionDivisor type spin default 225 min 50 max 1000".to_string()));
+    }
+
+    #[test]
+    fn reductions() {
+        let table = SearchParams::new().reduction_table();
+        assert_eq!(table[1][1], 0);
+        assert_eq!(table[3][3], 1);
+        assert!(table[20][40] > table[5][5]);
+        assert_eq!(SearchParams::disabled().reduction_table(), table);
+    }
+}
@@ -0,0 +1,902 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use crate::advanced::attack_info::AttackInfo;
+use crate::advanced::board::Board;
+use crate::advanced::move_list::MoveList;
+use crate::advanced::see::SEE_VALUE;
+use crate::eval::evaluator::evaluate;
+use crate::search::history::History;
+use crate::search::move_picker::{MovePicker, is_quiet};
+use crate::search::principal_variation::PrincipalVariation;
+use crate::search::score::{DRAW, INFINITE, MATE_BOUND, is_mate, mated_in};
+use crate::search::search_params::SearchParams;
+use crate::search::time_manager::{Clock, TimeManager};
+use crate::search::transposition_table::{Bound, TranspositionEntry, TranspositionTable};
+use crate::types::board_move::BoardMove;
+use crate::types::color::Color;
+use crate::types::piece_type::PieceType;
+use crate::types::rank::Rank;
+use crate::types::square::Square;
+use crate::uci::uci_command::GoParams;
+
+#[derive(Clone, Debug)]
+pub struct SearchResult {
+    pub best_move: BoardMove,
+    pub score: i32,
+    pub depth: u8,
+    pub nodes: u64,
+    pub hashfull: u16,
+    pub principal_variation: Vec<BoardMove>,
+}
+
+impl SearchResult {
+    pub fn new() -> Self {
+        SearchResult {
+            best_move: BoardMove::NONE,
+            score: -INFINITE,
+            depth: 0,
+            nodes: 0,
+            hashfull: 0,
+            principal_variation: Vec::new(),
+        }
+    }
+}
+
+pub struct Searcher {
+    attack_info: AttackInfo,
+    move_list: MoveList,
+    principal_variation: PrincipalVariation,
+    transposition_table: Arc<TranspositionTable>,
+    history: History,
+    // Continuation key of the move played at each ply
+    continuation_keys: [usize; MoveList::MAX_PLIES],
+    params: SearchParams,
+    reductions: [[u8; SearchParams::REDUCTION_TABLE_SIZE]; SearchParams::REDUCTION_TABLE_SIZE],
+    // Set while a null move cutoff is verified
+    null_move_disabled: bool,
+    // Move left out of the node while its singularity is verified
+    excluded_moves: [BoardMove; MoveList::MAX_PLIES],
+    // Square captured on by the move played at each ply
+    capture_squares: [Option<Square>; MoveList::MAX_PLIES],
+    // Extensions granted in the current root iteration and the nodes counted before it started
+    iteration_extensions: u64,
+    iteration_start_nodes: u64,
+    time_manager: TimeManager,
+    nodes: u64,
+    // One per thread, indexed by the thread index
+    node_counters: Arc<Vec<NodeCounter>>,
+    limit_reached: bool,
+    stop: Arc<AtomicBool>,
+    // Set once the master is done, so the helpers stop with it
+    abort: Arc<AtomicBool>,
+    // Zero for the master thread
+    thread_index: usize,
+    helpers: Vec<Searcher>,
+}
+
+// Aligned to a cache line, so threads counting nodes do not slow each other down
+#[repr(align(64))]
+struct NodeCounter(AtomicU64);
+
+impl Searcher {
+    // Captures that can not bring the score close to alpha are skipped
+    const DELTA_MARGIN: i32 = 200;
+    // Nodes between clock reads
+    const TIME_CHECK_MASK: u64 = 1023;
+    // Nodes between sums of the other threads' nodes
+    const NODE_CHECK_MASK: u64 = 63;
+    // Quiet moves that get a penalty when a later one causes a cutoff
+    const MAX_QUIETS_SEARCHED: usize = 64;
+    // Helpers skip some iterations so they do not all search the same depth
+    const SKIP_SIZE: [usize; 20] = [1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 4, 4, 4];
+    const SKIP_PHASE: [usize; 20] = [0, 1, 0, 1, 2, 3, 0, 1, 2, 3, 4, 5, 0, 1, 2, 3, 4, 5, 6, 7];
+
+    pub const MAX_THREADS: usize = 128;
+
+    pub fn new() -> Self {
+        Searcher::with_table(Arc::new(TranspositionTable::new(TranspositionTable::DEFAULT_SIZE_MB)))
+    }
+
+    fn with_table(transposition_table: Arc<TranspositionTable>) -> Self {
+        Searcher {
+            attack_info: AttackInfo::new(),
+            move_list: MoveList::new(),
+            principal_variation: PrincipalVariation::new(),
+            transposition_table,
+            history: History::new(),
+            continuation_keys: [History::NO_CONTINUATION; MoveList::MAX_PLIES],
+            params: SearchParams::new(),
+            reductions: SearchParams::new().reduction_table(),
+            null_move_disabled: false,
+            excluded_moves: [BoardMove::NONE; MoveList::MAX_PLIES],
+            capture_squares: [None; MoveList::MAX_PLIES],
+            iteration_extensions: 0,
+            iteration_start_nodes: 0,
+            time_manager: TimeManager::new(),
+            nodes: 0,
+            node_counters: Arc::new(vec![NodeCounter(AtomicU64::new(0))]),
+            limit_reached: false,
+            stop: Arc::new(AtomicBool::new(false)),
+            abort: Arc::new(AtomicBool::new(false)),
+            thread_index: 0,
+            helpers: Vec::new(),
+        }
+    }
+
+    #[inline]
+    pub fn threads(&self) -> usize {
+        self.helpers.len() + 1
+    }
+
+    // Helpers share the transposition table and the stop flag, the rest of their state is their own
+    pub fn set_threads(&mut self, threads: usize) {
+        let threads = threads.clamp(1, Searcher::MAX_THREADS);
+        self.helpers.truncate(threads - 1);
+        while self.helpers.len() < threads - 1 {
+            let mut helper = Searcher::with_table(self.transposition_table.clone());
+            helper.stop = self.stop.clone();
+            helper.abort = self.abort.clone();
+            helper.thread_index = self.helpers.len() + 1;
+            helper.set_params(self.params);
+            self.helpers.push(helper);
+        }
+        self.node_counters = Arc::new((0..threads).map(|_| NodeCounter(AtomicU64::new(0))).collect());
+        for helper in self.helpers.iter_mut() {
+            helper.node_counters = self.node_counters.clone();
+        }
+    }
+
+    // Shared flag that interrupts the search as soon as it is set
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    // Limits apply to every following search, on top of the depth and the stop flag
+    pub fn set_node_limit(&mut self, node_limit: Option<u64>) {
+        self.time_manager.set_node_limit(node_limit);
+    }
+
+    pub fn set_time_limit(&mut self, time_limit: Option<Duration>) {
+        self.time_manager.set_move_time(time_limit);
+    }
+
+    // Replaces every limit with the ones of the go command, for the side to move
+    pub fn set_go_params(&mut self, params: &GoParams, color: Color) {
+        self.time_manager.configure(params, color);
+    }
+
+    pub fn set_clock(&mut self, clock: Box<dyn Clock>) {
+        self.time_manager.set_clock(clock);
+    }
+
+    #[inline]
+    pub fn params(&self) -> &SearchParams {
+        &self.params
+    }
+
+    pub fn set_params(&mut self, params: SearchParams) {
+        self.params = params;
+        self.reductions = params.reduction_table();
+        for helper in self.helpers.iter_mut() {
+            helper.set_params(params);
+        }
+    }
+
+    pub fn resize_transposition_table(&mut self, size_mb: usize) {
+        self.transposition_table = Arc::new(TranspositionTable::new(size_mb));
+        for helper in self.helpers.iter_mut() {
+            helper.transposition_table = self.transposition_table.clone();
+        }
+    }
+
+    pub fn clear_transposition_table(&mut self) {
+        self.transposition_table.clear();
+    }
+
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+        for helper in self.helpers.iter_mut() {
+            helper.history.clear();
+        }
+    }
+
+    pub fn search(&mut self, board: &Board, max_depth: u8) -> SearchResult {
+        self.iterative_deepening(board, max_depth, |_| {})
+    }
+
+    // Helpers search the same position until the master is done, only the master reports
+    pub fn iterative_deepening<F: FnMut(&SearchResult)>(&mut self, board: &Board, max_depth: u8, on_iteration: F) -> SearchResult {
+        self.transposition_table.new_search();
+        for node_counter in self.node_counters.iter() {
+            node_counter.0.store(0, Ordering::Relaxed);
+        }
+        let max_depth = max_depth.min(self.time_manager.max_depth());
+        let mut helpers = std::mem::take(&mut self.helpers);
+        // Every thread checks the node limit, the master alone could be too late
+        for helper in helpers.iter_mut() {
+            helper.time_manager.set_node_limit(self.time_manager.node_limit());
+        }
+
+        let mut result = thread::scope(|scope| {
+            for helper in helpers.iter_mut() {
+                scope.spawn(move || helper.search_iterations(board, max_depth, |_| {}));
+            }
+            let result = self.search_iterations(board, max_depth, on_iteration);
+            self.abort.store(true, Ordering::Relaxed);
+            result
+        });
+        self.abort.store(false, Ordering::Relaxed);
+        self.helpers = helpers;
+        result.nodes = self.total_nodes();
+        result
+    }
+
+    fn search_iterations<F: FnMut(&SearchResult)>(&mut self, board: &Board, max_depth: u8, mut on_iteration: F) -> SearchResult {
+        self.nodes = 0;
+        self.limit_reached = false;
+        self.time_manager.start();
+        self.history.new_search();
+        let mut board = board.clone_position();
+        let mut result = SearchResult::new();
+
+        for depth in 1..=max_depth {
+            if self.skip_depth(depth) {
+                continue;
+            }
+            self.iteration_extensions = 0;
+            self.iteration_start_nodes = self.nodes;
+            let score = self.negamax(&mut board, depth, 0, -INFINITE, INFINITE);
+            let best_move = self.principal_variation.best_move();
+            // An interrupted iteration is only trusted when nothing else is available
+            if self.should_stop() && (result.best_move != BoardMove::NONE || best_move == BoardMove::NONE) {
+                break;
+            }
+            result = SearchResult {
+                best_move,
+                score,
+                depth,
+                nodes: self.total_nodes(),
+                hashfull: self.transposition_table.hashfull(),
+                principal_variation: self.principal_variation.line(0),
+            };
+            on_iteration(&result);
+            if self.should_stop() || !self.time_manager.iteration_done(&result) {
+                break;
+            }
+        }
+        result
+    }
+
+    #[inline]
+    fn skip_depth(&self, depth: u8) -> bool {
+        if self.thread_index == 0 {
+            return false;
+        }
+        let index = (self.thread_index - 1) % Searcher::SKIP_SIZE.len();
+        ((depth as usize + Searcher::SKIP_PHASE[index]) / Searcher::SKIP_SIZE[index]) & 1 == 1
+    }
+
+    #[inline]
+    fn should_stop(&self) -> bool {
+        self.limit_reached || self.stop.load(Ordering::Relaxed) || self.abort.load(Ordering::Relaxed)
+    }
+
+    #[inline]
+    fn total_nodes(&self) -> u64 {
+        self.node_counters.iter().map(|node_counter| node_counter.0.load(Ordering::Relaxed)).sum()
+    }
+
+    #[inline]
+    fn count_node(&mut self) {
+        self.nodes += 1;
+        self.node_counters[self.thread_index].0.store(self.nodes, Ordering::Relaxed);
+        // Nodes of the other threads are only added up from time to time
+        let node_limit_reached = if self.node_counters.len() == 1 {
+            self.time_manager.node_limit_reached(self.nodes)
+        } else {
+            self.nodes & Searcher::NODE_CHECK_MASK == 0 && self.time_manager.node_limit_reached(self.total_nodes())
+        };
+        if node_limit_reached || self.nodes & Searcher::TIME_CHECK_MASK == 0 && self.time_manager.time_limit_reached() {
+            self.limit_reached = true;
+        }
+    }
+
+    fn negamax(&mut self, board: &mut Board, depth: u8, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        if depth == 0 {
+            return self.quiescence(board, ply, alpha, beta);
+        }
+
+        self.principal_variation.clear(ply);
+        if self.should_stop() {
+            return DRAW;
+        }
+        self.count_node();
+
+        if ply > 0 && board.rule_50 >= 100 {
+            return DRAW;
+        }
+
+        let is_pv = beta - alpha > 1;
+        let in_check = board.check_bitboard.is_not_empty();
+        // The entry belongs to the node with every move, it can not be used while one is excluded
+        let excluded_move = self.excluded_moves[ply];
+        let mut hash_move = BoardMove::NONE;
+        let mut hash_entry = None;
+        if excluded_move == BoardMove::NONE {
+            hash_entry = self.transposition_table.probe(&board.zkey, ply);
+        }
+        if let Some(entry) = hash_entry {
+            hash_move = entry.board_move();
+            if ply > 0 && !is_pv && entry.depth() >= depth {
+                let score = entry.score();
+                match entry.bound() {
+                    Bound::Exact => return score,
+                    Bound::Lower if score >= beta => return score,
+                    Bound::Upper if score <= alpha => return score,
+                    _ => {}
+                }
+            }
+        }
+
+        let static_eval = if in_check {
+            -INFINITE
+        } else {
+            evaluate(board)
+        };
+        if ply > 0 && !is_pv && !in_check && excluded_move == BoardMove::NONE {
+            if let Some(score) = self.prune_node(board, depth, ply, alpha, beta, static_eval) {
+                return score;
+            }
+        }
+
+        if !self.move_list.start_ply() {
+            return evaluate(board);
+        }
+        let continuations = self.continuations(ply);
+        let mut move_picker = MovePicker::new(hash_move, &self.history, ply, continuations);
+        let mut quiets_searched = [BoardMove::NONE; Searcher::MAX_QUIETS_SEARCHED];
+        let mut quiets_count = 0;
+
+        let original_alpha = alpha;
+        let mut best_score = -INFINITE;
+        let mut best_move = BoardMove::NONE;
+        let mut moves_searched = 0;
+
+        while let Some(board_move) = move_picker.next(board, &mut self.move_list, &mut self.attack_info, &self.history) {
+            if board_move == excluded_move {
+                continue;
+            }
+            let quiet = is_quiet(board, &board_move);
+            // Only once a move keeps us from getting mated
+            if ply > 0 && quiet && !in_check && best_score > -MATE_BOUND &&
+                self.prune_move(depth, quiets_count, alpha, static_eval) {
+                continue;
+            }
+
+            let can_extend = self.extension_allowed(depth, ply);
+            let mut extension = 0;
+            if can_extend && board_move == hash_move && ply > 0 &&
+                self.is_singular(board, depth, ply, &board_move, hash_entry) {
+                extension = 1;
+            }
+            if can_extend && extension == 0 && self.extends_move(board, ply, &board_move, quiet) {
+                extension = 1;
+            }
+            self.capture_squares[ply] = if quiet { None } else { Some(board_move.square_to()) };
+            self.continuation_keys[ply] = History::continuation_key(board, &board_move);
+            if !board.do_move(&board_move) {
+                board.undo_move(&board_move);
+                continue;
+            }
+            let gives_check = board.check_bitboard.is_not_empty();
+            if can_extend && gives_check && self.params.check_extension {
+                extension = 1;
+            }
+            self.iteration_extensions += extension as u64;
+            let new_depth = depth - 1 + extension;
+
+            let score = if moves_searched == 0 {
+                -self.negamax(board, new_depth, ply + 1, -beta, -alpha)
+            } else {
+                let reduction = if quiet && !in_check && !gives_check {
+                    self.reduction(depth, moves_searched, is_pv)
+                } else {
+                    0
+                };
+                let mut score = -self.negamax(board, new_depth - reduction, ply + 1, -alpha - 1, -alpha);
+                if reduction > 0 && score > alpha {
+                    score = -self.negamax(board, new_depth, ply + 1, -alpha - 1, -alpha);
+                }
+                if score > alpha && score < beta {
+                    -self.negamax(board, new_depth, ply + 1, -beta, -alpha)
+                } else {
+                    score
+                }
+            };
+            board.undo_move(&board_move);
+            moves_searched += 1;
+
+            if self.should_stop() {
+                break;
+            }
+
+            if score > best_score {
+                best_score = score;
+                best_move = board_move;
+                if score > alpha {
+                    alpha = score;
+                    self.principal_variation.update(ply, board_move);
+                    if alpha >= beta {
+                        if quiet {
+                            self.history.update_quiets(board, ply, &continuations, &board_move,
+                                                       &quiets_searched[..quiets_count], depth);
+                        }
+                        break;
+                    }
+                }
+            }
+            if quiet && quiets_count < Searcher::MAX_QUIETS_SEARCHED {
+                quiets_searched[quiets_count] = board_move;
+                quiets_count += 1;
+            }
+        }
+        self.move_list.end_ply();
+
+        if moves_searched == 0 {
+            return if excluded_move != BoardMove::NONE {
+                // Nothing besides the excluded move, it is singular
+                alpha
+            } else if board.check_bitboard.is_not_empty() {
+                mated_in(ply)
+            } else {
+                DRAW
+            };
+        }
+
+        if !self.should_stop() && excluded_move == BoardMove::NONE {
+            let bound = if best_score >= beta {
+                Bound::Lower
+            } else if best_score > original_alpha {
+                Bound::Exact
+            } else {
+                Bound::Upper
+            };
+            self.transposition_table.store(&board.zkey, best_move, best_score, depth, bound, ply);
+        }
+        best_score
+    }
+
+    // Pruning before any move is searched, returns the score of the node when it can be skipped
+    fn prune_node(&mut self, board: &mut Board, depth: u8, ply: usize, alpha: i32, beta: i32, static_eval: i32) -> Option<i32> {
+        let depth_value = depth as i32;
+        if self.params.reverse_futility && depth_value <= self.params.reverse_futility_depth && !is_mate(beta) &&
+            static_eval - self.params.reverse_futility_margin * depth_value >= beta {
+            return Some(static_eval);
+        }
+
+        if self.params.razoring && depth_value <= self.params.razoring_depth &&
+            static_eval + self.params.razoring_margin * depth_value < alpha {
+            let score = self.quiescence(board, ply, alpha, alpha + 1);
+            if score <= alpha {
+                return Some(score);
+            }
+        }
+
+        // Two null moves in a row would only pass the turn back
+        let non_pawn_pieces = board.color_bitboard(&board.color_to_move)
+            .difference(&board.piece_bitboard(&board.color_to_move, &PieceType::PAWN))
+            .difference(&board.piece_bitboard(&board.color_to_move, &PieceType::KING))
+            .count() as i32;
+        if self.params.null_move && !self.null_move_disabled && depth_value >= self.params.null_move_min_depth &&
+            static_eval >= beta && non_pawn_pieces > 0 && self.continuation_keys[ply - 1] != History::NO_CONTINUATION {
+            let reduction = self.params.null_move_reduction + depth_value / self.params.null_move_depth_divisor;
+            let null_depth = (depth_value - 1 - reduction).max(0) as u8;
+            self.continuation_keys[ply] = History::NO_CONTINUATION;
+            board.do_null_move();
+            let score = -self.negamax(board, null_depth, ply + 1, -beta, -beta + 1);
+            board.undo_null_move();
+            if self.should_stop() {
+                return Some(DRAW);
+            }
+            if score >= beta {
+                // Mates found after passing the turn are not proven
+                let score = if is_mate(score) { beta } else { score };
+                if non_pawn_pieces > self.params.null_move_verification_pieces || null_depth == 0 {
+                    return Some(score);
+                }
+                // Zugzwang is likely with few pieces, confirm with a normal reduced search
+                self.null_move_disabled = true;
+                let verification = self.negamax(board, null_depth, ply, beta - 1, beta);
+                self.null_move_disabled = false;
+                if verification >= beta {
+                    return Some(score);
+                }
+            }
+        }
+        None
+    }
+
+    // Quiet moves that are unlikely to raise alpha
+    #[inline]
+    fn prune_move(&self, depth: u8, quiets_count: usize, alpha: i32, static_eval: i32) -> bool {
+        let depth = depth as i32;
+        if self.params.late_move_pruning && depth <= self.params.late_move_pruning_depth &&
+            quiets_count as i32 >= self.params.late_move_pruning_base + depth * depth {
+            return true;
+        }
+        self.params.futility && depth <= self.params.futility_depth &&
+            static_eval + self.params.futility_base + self.params.futility_margin * depth <= alpha
+    }
+
+    // Extensions of a root iteration are limited to a share of its nodes, so they can not explode,
+    // and extended lines stay well inside the ply tables
+    #[inline]
+    fn extension_allowed(&self, depth: u8, ply: usize) -> bool {
+        ply + (depth as usize) < MoveList::MAX_PLIES / 2 &&
+            self.iteration_extensions * 100 < (self.nodes - self.iteration_start_nodes) * self.params.extension_budget as u64
+    }
+
+    // The hash move is singular when every other move fails low against a margin below its score
+    fn is_singular(&mut self, board: &mut Board, depth: u8, ply: usize, board_move: &BoardMove,
+                   hash_entry: Option<TranspositionEntry>) -> bool {
+        let entry = match hash_entry {
+            Some(entry) => entry,
+            None => return false,
+        };
+        let depth_value = depth as i32;
+        if !self.params.singular_extension || depth_value < self.params.singular_depth ||
+            entry.bound() == Bound::Upper || entry.depth() as i32 + 3 < depth_value || is_mate(entry.score()) {
+            return false;
+        }
+        let singular_beta = entry.score() - self.params.singular_margin * depth_value;
+        self.excluded_moves[ply] = *board_move;
+        let score = self.negamax(board, (depth - 1) / 2, ply, singular_beta - 1, singular_beta);
+        self.excluded_moves[ply] = BoardMove::NONE;
+        !self.should_stop() && score < singular_beta
+    }
+
+    // Recaptures and passed pawn pushes close to promotion, checks are only known after the move
+    #[inline]
+    fn extends_move(&self, board: &Board, ply: usize, board_move: &BoardMove, quiet: bool) -> bool {
+        let square_to = board_move.square_to();
+        if self.params.recapture_extension && !quiet && ply > 0 && self.capture_squares[ply - 1] == Some(square_to) {
+            return true;
+        }
+        let color = board.color_to_move;
+        self.params.passed_pawn_extension && board.piece_type(&board_move.square_from()) == PieceType::PAWN &&
+            square_to.to_rank().relative(&color) >= Rank::RANK_6 &&
+            square_to.passed_pawn_mask(&color).intersect(&board.piece_bitboard(&color.reverse(), &PieceType::PAWN)).is_empty()
+    }
+
+    // Never drops the search straight into quiescence
+    #[inline]
+    fn reduction(&self, depth: u8, moves_searched: usize, is_pv: bool) -> u8 {
+        if !self.params.late_move_reductions || (depth as i32) < self.params.late_move_reduction_depth ||
+            (moves_searched as i32) < self.params.late_move_reduction_moves {
+            return 0;
+        }
+        let last = SearchParams::REDUCTION_TABLE_SIZE - 1;
+        let mut reduction = self.reductions[(depth as usize).min(last)][moves_searched.min(last)];
+        if is_pv && reduction > 0 {
+            reduction -= 1;
+        }
+        reduction.min(depth.saturating_sub(2))
+    }
+
+    // Keys of the last two moves played, the most recent first
+    #[inline]
+    fn continuations(&self, ply: usize) -> [usize; 2] {
+        [
+            if ply >= 1 { self.continuation_keys[ply - 1] } else { History::NO_CONTINUATION },
+            if ply >= 2 { self.continuation_keys[ply - 2] } else { History::NO_CONTINUATION },
+        ]
+    }
+
+    // Searches noisy moves until the position is quiet, all moves are searched when in check
+    fn quiescence(&mut self, board: &mut Board, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        self.principal_variation.clear(ply);
+        if self.should_stop() {
+            return DRAW;
+        }
+        self.count_node();
+
+        let in_check = board.check_bitboard.is_not_empty();
+        let stand_pat = if in_check {
+            -INFINITE
+        } else {
+            evaluate(board)
+        };
+        if stand_pat >= beta {
+            return stand_pat;
+        }
+        if stand_pat > alpha {
+            alpha = stand_pat;
+        }
+
+        if !self.move_list.start_ply() {
+            return evaluate(board);
+        }
+        let mut move_picker = if in_check {
+            MovePicker::new(BoardMove::NONE, &self.history, ply, self.continuations(ply))
+        } else {
+            MovePicker::noisy(BoardMove::NONE)
+        };
+
+        let mut best_score = stand_pat;
+        let mut moves_searched = 0;
+
+        while let Some(board_move) = move_picker.next(board, &mut self.move_list, &mut self.attack_info, &self.history) {
+            if !in_check && !board_move.move_type().is_promotion() {
+                let captured = if board_move.move_type().is_passant() {
+                    PieceType::PAWN
+                } else {
+                    board.piece_type(&board_move.square_to())
+                };
+                if stand_pat + SEE_VALUE[captured.to_usize()] + Searcher::DELTA_MARGIN <= alpha {
+                    continue;
+                }
+                if !board.see_ge(&board_move, 0) {
+                    continue;
+                }
+            }
+
+            self.continuation_keys[ply] = History::continuation_key(board, &board_move);
+            if !board.do_move(&board_move) {
+                board.undo_move(&board_move);
+                continue;
+            }
+            let score = -self.quiescence(board, ply + 1, -beta, -alpha);
+            board.undo_move(&board_move);
+            moves_searched += 1;
+
+            if self.should_stop() {
+                break;
+            }
+
+            if score > best_score {
+                best_score = score;
+                if score > alpha {
+                    alpha = score;
+                    self.principal_variation.update(ply, board_move);
+                    if alpha >= beta {
+                        break;
+                    }
+                }
+            }
+        }
+        self.move_list.end_ply();
+
+        if in_check && moves_searched == 0 {
+            return mated_in(ply);
+        }
+        best_score
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Instant;
+
+    use super::*;
+    use crate::search::score::{mate_in, mated_in};
+    use crate::types::square::Square;
+
+    fn search(fen: &str, depth: u8) -> SearchResult {
+        let board = Board::from_fen(fen);
+        Searcher::new().search(&board, depth)
+    }
+
+    #[test]
+    fn mate_in_one() {
+        let result = search("6k1/5ppp/8/8/8/8/8/R5K1 w - -", 2);
+        assert_eq!(result.best_move.to_string(), "a1a8");
+        assert_eq!(result.score, mate_in(1));
+    }
+
+    #[test]
+    fn mated() {
+        let result = search("R5k1/5ppp/8/8/8/8/8/6K1 b - -", 1);
+        assert_eq!(result.best_move, BoardMove::NONE);
+        assert_eq!(result.score, mated_in(0));
+    }
+
+    #[test]
+    fn stalemate() {
+        let result = search("7k/5Q2/6K1/8/8/8/8/8 b - -", 3);
+        assert_eq!(result.score, DRAW);
+    }
+
+    #[test]
+    fn wins_hanging_queen() {
+        let result = search("4k3/8/8/3q4/8/8/3R4/4K3 w - -", 2);
+        assert_eq!(result.best_move.to_string(), "d2d5");
+    }
+
+    #[test]
+    fn avoids_defended_pawn() {
+        let result = search("3rk3/8/8/3p4/8/8/8/3QK3 w - -", 1);
+        assert_ne!(result.best_move.to_string(), "d1d5");
+    }
+
+    #[test]
+    fn quiescence_resolves_captures() {
+        let mut board = Board::from_fen("3rk3/8/8/3p4/8/8/8/3QK3 w - -");
+        board.do_move(&BoardMove::build_normal(&Square::D1, &Square::D5));
+        let mut searcher = Searcher::new();
+        // The queen is lost to the rook recapture
+        assert!(-searcher.quiescence(&mut board, 1, -INFINITE, INFINITE) < -500);
+    }
+
+    #[test]
+    fn quiescence_evades_checks() {
+        let mut board = Board::from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - -");
+        let mut searcher = Searcher::new();
+        assert_eq!(searcher.quiescence(&mut board, 0, -INFINITE, INFINITE), mated_in(0));
+    }
+
+    #[test]
+    fn principal_variation_starts_with_best_move() {
+        let result = search("6k1/5ppp/8/8/8/8/8/R5K1 w - -", 3);
+        assert_eq!(result.principal_variation[0], result.best_move);
+        assert_eq!(result.depth, 3);
+        assert!(result.nodes > 0);
+    }
+
+    #[test]
+    fn stores_root_in_transposition_table() {
+        let board = Board::from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - -");
+        let mut searcher = Searcher::new();
+        let result = searcher.search(&board, 3);
+        let entry = searcher.transposition_table.probe(&board.zkey, 0).unwrap();
+        assert_eq!(entry.board_move(), result.best_move);
+        assert_eq!(entry.depth(), 3);
+        assert_eq!(entry.bound(), Bound::Exact);
+    }
+
+    #[test]
+    fn transposition_table_keeps_results() {
+        let board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -");
+        let mut searcher = Searcher::new();
+        let first = searcher.search(&board, 4);
+        let second = searcher.search(&board, 4);
+        assert_eq!(first.score, second.score);
+        assert!(second.nodes < first.nodes);
+    }
+
+    #[test]
+    fn stop_flag_interrupts() {
+        let board = Board::default();
+        let mut searcher = Searcher::new();
+        searcher.stop_flag().store(true, Ordering::Relaxed);
+        let result = searcher.search(&board, 10);
+        assert_eq!(result.depth, 0);
+    }
+
+    #[test]
+    fn limits() {
+        let board = Board::default();
+        let mut searcher = Searcher::new();
+        searcher.set_node_limit(Some(5000));
+        let result = searcher.search(&board, 30);
+        assert!(result.depth < 30);
+        assert!(result.nodes <= 5000);
+        assert_ne!(result.best_move, BoardMove::NONE);
+
+        searcher.set_node_limit(None);
+        searcher.set_time_limit(Some(Duration::from_millis(20)));
+        let start = Instant::now();
+        let result = searcher.search(&board, 60);
+        assert!(result.depth < 60);
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn go_params() {
+        let board = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - -");
+        let mut searcher = Searcher::new();
+        searcher.set_go_params(&GoParams { mate: Some(1), ..GoParams::default() }, Color::White);
+        let result = searcher.search(&board, TimeManager::MAX_DEPTH);
+        assert_eq!(result.score, mate_in(1));
+        assert!(result.depth <= 2);
+
+        searcher.set_go_params(&GoParams { depth: Some(3), ..GoParams::default() }, Color::White);
+        assert_eq!(searcher.search(&Board::default(), TimeManager::MAX_DEPTH).depth, 3);
+
+        searcher.set_go_params(&GoParams { wtime: Some(1000), btime: Some(50), ..GoParams::default() }, Color::Black);
+        let start = Instant::now();
+        let result = searcher.search(&Board::default(), TimeManager::MAX_DEPTH);
+        assert_ne!(result.best_move, BoardMove::NONE);
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn switchable_pruning() {
+        let board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -");
+        let mut searcher = Searcher::new();
+        searcher.set_params(SearchParams::disabled());
+        let full = searcher.search(&board, 5);
+        searcher.clear_transposition_table();
+        searcher.clear_history();
+        searcher.set_params(SearchParams::new());
+        let selective = searcher.search(&board, 5);
+        assert!(selective.nodes < full.nodes);
+
+        let board = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - -");
+        assert_eq!(searcher.search(&board, 4).score, mate_in(1));
+        searcher.set_params(SearchParams::disabled());
+        assert_eq!(searcher.search(&board, 4).score, mate_in(1));
+    }
+
+    #[test]
+    fn check_extension() {
+        // The queen sacrifice only mates after a line of checks longer than the search depth
+        let board = Board::from_fen("r1b2rk1/pp1p1pp1/1b1p2B1/n1qQ2p1/8/5N2/P3RPPP/4R1K1 w - -");
+        let mut searcher = Searcher::new();
+        searcher.set_params(SearchParams::disabled());
+        let plain = searcher.search(&board, 4);
+        assert!(!is_mate(plain.score));
+
+        let checks = SearchParams { check_extension: true, ..SearchParams::disabled() };
+        searcher.clear_transposition_table();
+        searcher.set_params(checks);
+        let extended = searcher.search(&board, 4);
+        assert_eq!(extended.best_move.to_string(), "d5f7");
+        assert!(extended.score > MATE_BOUND);
+
+        // Without a budget nothing is extended
+        searcher.clear_transposition_table();
+        searcher.set_params(SearchParams { extension_budget: 0, ..checks });
+        assert_eq!(searcher.search(&board, 4).score, plain.score);
+
+        // The budget is shared by every line of the iteration
+        searcher.clear_transposition_table();
+        searcher.set_params(SearchParams { extension_budget: 1, ..checks });
+        searcher.search(&board, 5);
+        assert!(searcher.iteration_extensions > 0);
+        assert!(searcher.iteration_extensions * 100 <= searcher.nodes - searcher.iteration_start_nodes + 100);
+    }
+
+    #[test]
+    fn passed_pawn_extension() {
+        let mut searcher = Searcher::new();
+        searcher.set_params(SearchParams { passed_pawn_extension: true, ..SearchParams::disabled() });
+        let extends = |fen: &str, square_from: Square, square_to: Square| {
+            searcher.extends_move(&Board::from_fen(fen), 0, &BoardMove::build_normal(&square_from, &square_to), true)
+        };
+        assert!(extends("4k3/8/8/1P6/8/8/8/4K3 w - -", Square::B5, Square::B6));
+        assert!(extends("4k3/8/8/8/8/1p6/8/4K3 b - -", Square::B3, Square::B2));
+        // A pawn on an adjacent file can still stop it
+        assert!(!extends("4k3/p7/8/1P6/8/8/8/4K3 w - -", Square::B5, Square::B6));
+        assert!(!extends("4k3/8/8/8/1P6/8/8/4K3 w - -", Square::B4, Square::B5));
+    }
+
+    #[test]
+    fn lazy_smp() {
+        let board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -");
+        let mut searcher = Searcher::new();
+        searcher.set_threads(4);
+        assert_eq!(searcher.threads(), 4);
+        let result = searcher.search(&board, 6);
+        assert_eq!(result.depth, 6);
+        assert!(board.legal_moves(&mut MoveList::new(), &mut AttackInfo::new()).contains(&result.best_move));
+        // Helpers count towards the reported nodes
+        assert!(result.nodes > searcher.nodes);
+
+        let board = Board::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - -");
+        assert_eq!(searcher.search(&board, 4).score, mate_in(1));
+
+        searcher.set_node_limit(Some(20000));
+        let result = searcher.search(&Board::default(), 30);
+        assert!(result.depth < 30);
+        assert!(result.nodes < 20000 + 4 * (Searcher::TIME_CHECK_MASK + 1));
+
+        searcher.set_node_limit(None);
+        searcher.stop_flag().store(true, Ordering::Relaxed);
+        assert_eq!(searcher.search(&Board::default(), 10).depth, 0);
+        searcher.set_threads(0);
+        assert_eq!(searcher.threads(), 1);
+    }
+}
@@ -0,0 +1,369 @@
+use std::time::{Duration, Instant};
+
+use crate::advanced::move_list::MoveList;
+use crate::search::score::{is_mate, mate_moves};
+use crate::search::searcher::SearchResult;
+use crate::types::board_move::BoardMove;
+use crate::types::color::Color;
+use crate::uci::uci_command::GoParams;
+
+// Time elapsed since an arbitrary origin, it never goes back
+pub trait Clock: Send {
+    fn elapsed(&self) -> Duration;
+}
+
+pub struct MonotonicClock {
+    origin: Instant,
+}
+
+impl MonotonicClock {
+    pub fn new() -> Self {
+        MonotonicClock {
+            origin: Instant::now(),
+        }
+    }
+}
+
+impl Clock for MonotonicClock {
+    #[inline]
+    fn elapsed(&self) -> Duration {
+        self.origin.elapsed()
+    }
+}
+
+pub struct TimeManager {
+    clock: Box<dyn Clock>,
+    start: Duration,
+    iteration_start: Duration,
+    // Checked between iterations, extended up to the hard limit when the search is unstable
+    soft_limit: Option<Duration>,
+    // Checked while searching
+    hard_limit: Option<Duration>,
+    node_limit: Option<u64>,
+    mate: Option<u8>,
+    max_depth: u8,
+    best_move: BoardMove,
+    score: i32,
+    // Percentage, halved every iteration
+    best_move_changes: u32,
+}
+
+impl TimeManager {
+    pub const MAX_DEPTH: u8 = (MoveList::MAX_PLIES - 1) as u8;
+
+    // Lost between the engine and the gui on every move
+    const MOVE_OVERHEAD_MS: u64 = 20;
+    // Expected number of moves left when the time control does not say
+    const DEFAULT_MOVES_TO_GO: u64 = 30;
+    const MAX_MOVES_TO_GO: u64 = 50;
+    const INCREMENT_PERCENT: u64 = 75;
+    // Share of the remaining time a single move may use
+    const MAX_SOFT_PERCENT: u64 = 50;
+    const MAX_HARD_PERCENT: u64 = 80;
+    const HARD_LIMIT_FACTOR: u64 = 4;
+    // Each iteration is expected to take this much longer than the previous one
+    const BRANCHING_FACTOR: u32 = 2;
+    const BEST_MOVE_CHANGE_PERCENT: u32 = 100;
+    const MAX_INSTABILITY_PERCENT: u32 = 100;
+    // Score drop in centipawns giving the largest extension, worth half the soft limit
+    const MAX_SCORE_DROP: i32 = 100;
+
+    pub fn new() -> Self {
+        TimeManager::with_clock(Box::new(MonotonicClock::new()))
+    }
+
+    pub fn with_clock(clock: Box<dyn Clock>) -> Self {
+        TimeManager {
+            clock,
+            start: Duration::ZERO,
+            iteration_start: Duration::ZERO,
+            soft_limit: None,
+            hard_limit: None,
+            node_limit: None,
+            mate: None,
+            max_depth: TimeManager::MAX_DEPTH,
+            best_move: BoardMove::NONE,
+            score: 0,
+            best_move_changes: 0,
+        }
+    }
+
+    pub fn set_clock(&mut self, clock: Box<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    #[inline]
+    pub fn node_limit(&self) -> Option<u64> {
+        self.node_limit
+    }
+
+    pub fn set_node_limit(&mut self, node_limit: Option<u64>) {
+        self.node_limit = node_limit;
+    }
+
+    // Fixed time per move, never extended
+    pub fn set_move_time(&mut self, move_time: Option<Duration>) {
+        self.soft_limit = move_time;
+        self.hard_limit = move_time;
+    }
+
+    // Replaces every limit with the ones from the go command
+    pub fn configure(&mut self, params: &GoParams, color: Color) {
+        self.node_limit = params.nodes;
+        self.mate = params.mate;
+        self.max_depth = params.depth
+            .or_else(|| params.mate.map(|mate| mate.saturating_mul(2)))
+            .unwrap_or(TimeManager::MAX_DEPTH)
+            .min(TimeManager::MAX_DEPTH);
+
+        let (time, increment) = match color {
+            Color::White => (params.wtime, params.winc),
+            Color::Black => (params.btime, params.binc),
+        };
+        if params.infinite {
+            self.set_move_time(None);
+        } else if let Some(move_time) = params.move_time {
+            self.set_move_time(Some(Duration::from_millis(move_time.saturating_sub(TimeManager::MOVE_OVERHEAD_MS))));
+        } else if let Some(time) = time {
+            let (soft_limit, hard_limit) = TimeManager::allocate(time, increment.unwrap_or(0), params.moves_to_go);
+            self.soft_limit = Some(Duration::from_millis(soft_limit));
+            self.hard_limit = Some(Duration::from_millis(hard_limit));
+        } else {
+            self.set_move_time(None);
+        }
+    }
+
+    // Soft and hard limits in milliseconds
+    fn allocate(time: u64, increment: u64, moves_to_go: Option<u64>) -> (u64, u64) {
+        let available = time.saturating_sub(TimeManager::MOVE_OVERHEAD_MS);
+        let moves_to_go = moves_to_go.unwrap_or(TimeManager::DEFAULT_MOVES_TO_GO)
+            .clamp(1, TimeManager::MAX_MOVES_TO_GO);
+        let soft_limit = (available / moves_to_go + increment * TimeManager::INCREMENT_PERCENT / 100)
+            .min(available * TimeManager::MAX_SOFT_PERCENT / 100);
+        let hard_limit = (soft_limit * TimeManager::HARD_LIMIT_FACTOR)
+            .min(available * TimeManager::MAX_HARD_PERCENT / 100);
+        (soft_limit, hard_limit)
+    }
+
+    #[inline]
+    pub fn soft_limit(&self) -> Option<Duration> {
+        self.soft_limit
+    }
+
+    #[inline]
+    pub fn hard_limit(&self) -> Option<Duration> {
+        self.hard_limit
+    }
+
+    #[inline]
+    pub fn max_depth(&self) -> u8 {
+        self.max_depth
+    }
+
+    pub fn start(&mut self) {
+        self.start = self.clock.elapsed();
+        self.iteration_start = Duration::ZERO;
+        self.best_move = BoardMove::NONE;
+        self.score = 0;
+        self.best_move_changes = 0;
+    }
+
+    #[inline]
+    pub fn elapsed(&self) -> Duration {
+        self.clock.elapsed().saturating_sub(self.start)
+    }
+
+    #[inline]
+    pub fn node_limit_reached(&self, nodes: u64) -> bool {
+        self.node_limit.is_some_and(|node_limit| nodes >= node_limit)
+    }
+
+    #[inline]
+    pub fn time_limit_reached(&self) -> bool {
+        self.hard_limit.is_some_and(|hard_limit| self.elapsed() >= hard_limit)
+    }
+
+    // Called after every completed iteration, returns whether the next one should start
+    pub fn iteration_done(&mut self, result: &SearchResult) -> bool {
+        let elapsed = self.elapsed();
+        let iteration_time = elapsed.saturating_sub(self.iteration_start);
+        self.iteration_start = elapsed;
+
+        self.best_move_changes /= 2;
+        let mut score_drop = 0;
+        if self.best_move != BoardMove::NONE {
+            if result.best_move != self.best_move {
+                self.best_move_changes += TimeManager::BEST_MOVE_CHANGE_PERCENT;
+            }
+            score_drop = (self.score - result.score).clamp(0, TimeManager::MAX_SCORE_DROP) as u32;
+        }
+        self.best_move = result.best_move;
+        self.score = result.score;
+
+        if self.mate.is_some_and(|mate| is_mate(result.score) &&
+            mate_moves(result.score) > 0 && mate_moves(result.score) <= mate as i32) {
+            return false;
+        }
+        if result.depth >= self.max_depth {
+            return false;
+        }
+        if let Some(hard_limit) = self.hard_limit {
+            if elapsed + iteration_time * TimeManager::BRANCHING_FACTOR > hard_limit {
+                return false;
+            }
+        }
+        if let Some(soft_limit) = self.soft_limit {
+            let scale = 100 + self.best_move_changes.min(TimeManager::MAX_INSTABILITY_PERCENT) +
+                score_drop * 50 / TimeManager::MAX_SCORE_DROP as u32;
+            let soft_limit = (soft_limit * scale / 100).min(self.hard_limit.unwrap_or(Duration::MAX));
+            if elapsed >= soft_limit {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use super::*;
+    use crate::search::score::mate_in;
+    use crate::types::square::Square;
+    use crate::uci::uci_command::UciCommand;
+
+    #[derive(Clone)]
+    struct FakeClock(Arc<AtomicU64>);
+
+    impl FakeClock {
+        fn new() -> Self {
+            FakeClock(Arc::new(AtomicU64::new(1000)))
+        }
+
+        fn advance(&self, millis: u64) {
+            self.0.fetch_add(millis, Ordering::Relaxed);
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn elapsed(&self) -> Duration {
+            Duration::from_millis(self.0.load(Ordering::Relaxed))
+        }
+    }
+
+    fn configured(params: &str, color: Color) -> (TimeManager, FakeClock) {
+        let clock = FakeClock::new();
+        let mut manager = TimeManager::with_clock(Box::new(clock.clone()));
+        let params = match UciCommand::parse(&format!("go {}", params)) {
+            UciCommand::Go(params) => params,
+            command => panic!("unexpected command {:?}", command),
+        };
+        manager.configure(&params, color);
+        manager.start();
+        (manager, clock)
+    }
+
+    fn result(best_move: BoardMove, score: i32, depth: u8) -> SearchResult {
+        SearchResult {
+            best_move,
+            score,
+            depth,
+            ..SearchResult::new()
+        }
+    }
+
+    fn e4() -> BoardMove {
+        BoardMove::build_normal(&Square::E2, &Square::E4)
+    }
+
+    fn d4() -> BoardMove {
+        BoardMove::build_normal(&Square::D2, &Square::D4)
+    }
+
+    #[test]
+    fn allocation() {
+        let (manager, _) = configured("wtime 60020 btime 1000 winc 1000", Color::White);
+        assert_eq!(manager.soft_limit(), Some(Duration::from_millis(2750)));
+        assert_eq!(manager.hard_limit(), Some(Duration::from_millis(11000)));
+
+        let (manager, _) = configured("wtime 60020 btime 1020 winc 1000", Color::Black);
+        assert_eq!(manager.soft_limit(), Some(Duration::from_millis(33)));
+        assert_eq!(manager.hard_limit(), Some(Duration::from_millis(132)));
+
+        let (manager, _) = configured("wtime 10020 movestogo 1", Color::White);
+        assert_eq!(manager.soft_limit(), Some(Duration::from_millis(5000)));
+        assert_eq!(manager.hard_limit(), Some(Duration::from_millis(8000)));
+
+        let (manager, _) = configured("wtime 10 winc 5000", Color::White);
+        assert_eq!(manager.soft_limit(), Some(Duration::ZERO));
+
+        let (manager, _) = configured("movetime 500 wtime 60000", Color::White);
+        assert_eq!(manager.soft_limit(), Some(Duration::from_millis(480)));
+        assert_eq!(manager.hard_limit(), Some(Duration::from_millis(480)));
+
+        let (manager, _) = configured("infinite wtime 60000", Color::White);
+        assert_eq!(manager.hard_limit(), None);
+        assert_eq!(manager.max_depth(), TimeManager::MAX_DEPTH);
+    }
+
+    #[test]
+    fn depth_nodes_and_mate() {
+        let (manager, _) = configured("depth 7 nodes 1000", Color::White);
+        assert_eq!(manager.max_depth(), 7);
+        assert_eq!(manager.hard_limit(), None);
+        assert!(!manager.node_limit_reached(999));
+        assert!(manager.node_limit_reached(1000));
+
+        let (mut manager, _) = configured("mate 2", Color::White);
+        assert_eq!(manager.max_depth(), 4);
+        assert!(manager.iteration_done(&result(e4(), mate_in(5), 2)));
+        assert!(!manager.iteration_done(&result(e4(), mate_in(3), 3)));
+    }
+
+    #[test]
+    fn limits() {
+        let (mut manager, clock) = configured("wtime 60020 winc 1000", Color::White);
+        clock.advance(100);
+        assert!(manager.iteration_done(&result(e4(), 20, 1)));
+        clock.advance(2700);
+        assert!(!manager.time_limit_reached());
+        // Soft limit reached
+        assert!(!manager.iteration_done(&result(e4(), 20, 2)));
+        clock.advance(8200);
+        assert!(manager.time_limit_reached());
+    }
+
+    #[test]
+    fn next_iteration_must_fit() {
+        let (mut manager, clock) = configured("movetime 1020", Color::White);
+        clock.advance(200);
+        assert!(manager.iteration_done(&result(e4(), 20, 1)));
+        clock.advance(300);
+        assert!(!manager.iteration_done(&result(e4(), 20, 2)));
+    }
+
+    #[test]
+    fn instability_extends() {
+        let (mut stable, stable_clock) = configured("wtime 60020 winc 1000", Color::White);
+        let (mut unstable, unstable_clock) = configured("wtime 60020 winc 1000", Color::White);
+        let (mut dropping, dropping_clock) = configured("wtime 60020 winc 1000", Color::White);
+        assert!(stable.iteration_done(&result(e4(), 20, 1)));
+        assert!(unstable.iteration_done(&result(e4(), 20, 1)));
+        assert!(dropping.iteration_done(&result(e4(), 20, 1)));
+
+        stable_clock.advance(3000);
+        unstable_clock.advance(3000);
+        dropping_clock.advance(3000);
+        assert!(!stable.iteration_done(&result(e4(), 20, 2)));
+        assert!(unstable.iteration_done(&result(d4(), 20, 2)));
+        assert!(dropping.iteration_done(&result(e4(), -100, 2)));
+
+        unstable_clock.advance(1000);
+        dropping_clock.advance(1000);
+        // Extensions fade once the search settles
+        assert!(unstable.iteration_done(&result(d4(), 20, 3)));
+        assert!(!dropping.iteration_done(&result(e4(), -100, 3)));
+    }
+}
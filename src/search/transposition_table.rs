@@ -0,0 +1,383 @@
+use std::mem::size_of;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+use crate::advanced::zobrist_key::ZobristKey;
+use crate::search::score::MATE_BOUND;
+use crate::types::board_move::BoardMove;
+
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Bound {
+    None = 0,
+    Upper = 1,
+    Lower = 2,
+    Exact = 3,
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct TranspositionEntry {
+    key: u64,
+    board_move: BoardMove,
+    score: i16,
+    depth: u8,
+    bound: Bound,
+    age: u8,
+}
+
+impl TranspositionEntry {
+    const EMPTY: TranspositionEntry = TranspositionEntry {
+        key: 0,
+        board_move: BoardMove::NONE,
+        score: 0,
+        depth: 0,
+        bound: Bound::None,
+        age: 0,
+    };
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.bound == Bound::None
+    }
+
+    #[inline]
+    pub fn board_move(&self) -> BoardMove {
+        self.board_move
+    }
+
+    #[inline]
+    pub fn score(&self) -> i32 {
+        self.score as i32
+    }
+
+    #[inline]
+    pub fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    #[inline]
+    pub fn bound(&self) -> Bound {
+        self.bound
+    }
+
+    // Move, score, depth, bound and age packed in the low 56 bits
+    #[inline]
+    fn pack(&self) -> u64 {
+        self.board_move.0 as u64 |
+            (self.score as u16 as u64) << 16 |
+            (self.depth as u64) << 32 |
+            (self.bound as u64) << 40 |
+            (self.age as u64) << 48
+    }
+
+    #[inline]
+    fn unpack(key: u64, data: u64) -> Self {
+        TranspositionEntry {
+            key,
+            board_move: BoardMove(data as u16),
+            score: (data >> 16) as u16 as i16,
+            depth: (data >> 32) as u8,
+            bound: match (data >> 40) as u8 {
+                1 => Bound::Upper,
+                2 => Bound::Lower,
+                3 => Bound::Exact,
+                _ => Bound::None,
+            },
+            age: (data >> 48) as u8,
+        }
+    }
+}
+
+// The key is stored xor the data, so an entry torn by a concurrent write does not match its key
+struct TranspositionSlot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+impl TranspositionSlot {
+    fn new() -> Self {
+        TranspositionSlot {
+            key: AtomicU64::new(0),
+            data: AtomicU64::new(0),
+        }
+    }
+
+    #[inline]
+    fn load(&self) -> TranspositionEntry {
+        let data = self.data.load(Ordering::Relaxed);
+        TranspositionEntry::unpack(self.key.load(Ordering::Relaxed) ^ data, data)
+    }
+
+    #[inline]
+    fn store(&self, entry: &TranspositionEntry) {
+        let data = entry.pack();
+        self.key.store(entry.key ^ data, Ordering::Relaxed);
+        self.data.store(data, Ordering::Relaxed);
+    }
+}
+
+// Shared by every search thread without locks
+pub struct TranspositionTable {
+    slots: Vec<TranspositionSlot>,
+    mask: usize,
+    age: AtomicU8,
+}
+
+impl TranspositionTable {
+    pub const DEFAULT_SIZE_MB: usize = 16;
+    pub const MIN_SIZE_MB: usize = 1;
+    pub const MAX_SIZE_MB: usize = 65536;
+
+    const BUCKET_SIZE: usize = 4;
+    const HASHFULL_SAMPLE: usize = 1000;
+
+    pub fn new(size_mb: usize) -> Self {
+        let mut result = TranspositionTable {
+            slots: Vec::new(),
+            mask: 0,
+            age: AtomicU8::new(0),
+        };
+        result.resize(size_mb);
+        result
+    }
+
+    // Number of entries is rounded down to a power of two
+    pub fn resize(&mut self, size_mb: usize) {
+        let size_mb = size_mb.clamp(TranspositionTable::MIN_SIZE_MB, TranspositionTable::MAX_SIZE_MB);
+        let bucket_bytes = TranspositionTable::BUCKET_SIZE * size_of::<TranspositionSlot>();
+        let buckets = (size_mb * 1024 * 1024 / bucket_bytes).next_power_of_two();
+        let buckets = if buckets * bucket_bytes > size_mb * 1024 * 1024 {
+            buckets / 2
+        } else {
+            buckets
+        };
+        self.slots = (0..buckets * TranspositionTable::BUCKET_SIZE).map(|_| TranspositionSlot::new()).collect();
+        self.mask = buckets - 1;
+        self.age = AtomicU8::new(0);
+    }
+
+    pub fn clear(&self) {
+        for slot in self.slots.iter() {
+            slot.store(&TranspositionEntry::EMPTY);
+        }
+        self.age.store(0, Ordering::Relaxed);
+    }
+
+    // Called once per search so older entries are replaced first
+    pub fn new_search(&self) {
+        self.age.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    #[inline]
+    fn bucket_index(&self, key: &ZobristKey) -> usize {
+        (key.to_u64() as usize & self.mask) * TranspositionTable::BUCKET_SIZE
+    }
+
+    pub fn probe(&self, key: &ZobristKey, ply: usize) -> Option<TranspositionEntry> {
+        let index = self.bucket_index(key);
+        for slot in self.slots[index..index + TranspositionTable::BUCKET_SIZE].iter() {
+            let entry = slot.load();
+            if !entry.is_empty() && entry.key == key.to_u64() {
+                let mut result = entry;
+                result.score = score_from_table(entry.score(), ply) as i16;
+                return Some(result);
+            }
+        }
+        None
+    }
+
+    pub fn store(&self, key: &ZobristKey, board_move: BoardMove, score: i32, depth: u8, bound: Bound, ply: usize) {
+        let index = self.bucket_index(key);
+        let age = self.age.load(Ordering::Relaxed);
+        let mut replace_index = index;
+        let mut replace_entry = TranspositionEntry::EMPTY;
+        let mut replace_value = i32::MAX;
+
+        for slot_index in index..index + TranspositionTable::BUCKET_SIZE {
+            let entry = self.slots[slot_index].load();
+            if entry.is_empty() || entry.key == key.to_u64() {
+                replace_index = slot_index;
+                replace_entry = entry;
+                break;
+            }
+            // Prefer replacing shallow entries left by older searches
+            let age_distance = age.wrapping_sub(entry.age) as i32;
+            let value = entry.depth as i32 - 8 * age_distance;
+            if value < replace_value {
+                replace_value = value;
+                replace_index = slot_index;
+                replace_entry = entry;
+            }
+        }
+
+        // Keep the previous move when this search did not find one
+        let board_move = if board_move == BoardMove::NONE && replace_entry.key == key.to_u64() {
+            replace_entry.board_move
+        } else {
+            board_move
+        };
+        self.slots[replace_index].store(&TranspositionEntry {
+            key: key.to_u64(),
+            board_move,
+            score: score_to_table(score, ply) as i16,
+            depth,
+            bound,
+            age,
+        });
+    }
+
+    // Permill of the sampled entries written by the current search
+    pub fn hashfull(&self) -> u16 {
+        let sample = TranspositionTable::HASHFULL_SAMPLE.min(self.slots.len());
+        let age = self.age.load(Ordering::Relaxed);
+        let used = self.slots[..sample].iter()
+            .map(|slot| slot.load())
+            .filter(|entry| !entry.is_empty() && entry.age == age)
+            .count();
+        (used * 1000 / sample) as u16
+    }
+}
+
+// Mate scores are stored relative to the node instead of the root
+#[inline]
+fn score_to_table(score: i32, ply: usize) -> i32 {
+    if score >= MATE_BOUND {
+        score + ply as i32
+    } else if score <= -MATE_BOUND {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+#[inline]
+fn score_from_table(score: i32, ply: usize) -> i32 {
+    if score >= MATE_BOUND {
+        score - ply as i32
+    } else if score <= -MATE_BOUND {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::thread;
+
+    use super::*;
+    use crate::search::score::{mate_in, mated_in};
+    use crate::types::square::Square;
+
+    #[test]
+    fn size_is_power_of_two() {
+        let table = TranspositionTable::new(1);
+        assert!(table.capacity().is_power_of_two());
+        assert!(table.capacity() * size_of::<TranspositionSlot>() <= 1024 * 1024);
+        let table = TranspositionTable::new(3);
+        assert!(table.capacity().is_power_of_two());
+        assert!(table.capacity() * size_of::<TranspositionSlot>() <= 3 * 1024 * 1024);
+    }
+
+    #[test]
+    fn store_and_probe() {
+        let table = TranspositionTable::new(1);
+        let key = ZobristKey(0x1234_5678_9abc_def0);
+        let board_move = BoardMove::build_normal(&Square::E2, &Square::E4);
+        assert!(table.probe(&key, 0).is_none());
+        table.store(&key, board_move, 35, 4, Bound::Exact, 0);
+        let entry = table.probe(&key, 0).unwrap();
+        assert_eq!(entry.board_move(), board_move);
+        assert_eq!(entry.score(), 35);
+        assert_eq!(entry.depth(), 4);
+        assert_eq!(entry.bound(), Bound::Exact);
+        assert!(table.probe(&ZobristKey(0x1234_5678_9abc_def1), 0).is_none());
+    }
+
+    #[test]
+    fn keeps_move_when_none_is_stored() {
+        let table = TranspositionTable::new(1);
+        let key = ZobristKey(42);
+        let board_move = BoardMove::build_normal(&Square::E2, &Square::E4);
+        table.store(&key, board_move, 35, 4, Bound::Lower, 0);
+        table.store(&key, BoardMove::NONE, 10, 5, Bound::Upper, 0);
+        assert_eq!(table.probe(&key, 0).unwrap().board_move(), board_move);
+    }
+
+    #[test]
+    fn mate_scores_are_ply_adjusted() {
+        let table = TranspositionTable::new(1);
+        let key = ZobristKey(7);
+        // Mate in 5 plies from the root found at ply 3
+        table.store(&key, BoardMove::NONE, mate_in(5), 2, Bound::Exact, 3);
+        assert_eq!(table.probe(&key, 3).unwrap().score(), mate_in(5));
+        assert_eq!(table.probe(&key, 1).unwrap().score(), mate_in(3));
+        table.store(&key, BoardMove::NONE, mated_in(6), 2, Bound::Exact, 4);
+        assert_eq!(table.probe(&key, 2).unwrap().score(), mated_in(4));
+    }
+
+    #[test]
+    fn replaces_shallow_old_entries() {
+        let table = TranspositionTable::new(1);
+        let buckets = table.mask + 1;
+        let keys: Vec<ZobristKey> = (0..5).map(|index| ZobristKey((index * buckets) as u64 + 1)).collect();
+        for (index, key) in keys[..4].iter().enumerate() {
+            table.store(key, BoardMove::NONE, 0, 10 + index as u8, Bound::Exact, 0);
+        }
+        table.store(&keys[4], BoardMove::NONE, 0, 1, Bound::Exact, 0);
+        assert!(table.probe(&keys[0], 0).is_none());
+        assert!(table.probe(&keys[4], 0).is_some());
+
+        table.new_search();
+        table.new_search();
+        table.store(&keys[0], BoardMove::NONE, 0, 1, Bound::Exact, 0);
+        assert!(table.probe(&keys[4], 0).is_none());
+        // Deep entries from older searches lose to fresh ones
+        table.store(&ZobristKey((5 * buckets) as u64 + 1), BoardMove::NONE, 0, 1, Bound::Exact, 0);
+        assert!(table.probe(&keys[0], 0).is_some());
+        assert!(table.probe(&keys[1], 0).is_none());
+    }
+
+    #[test]
+    fn hashfull() {
+        let table = TranspositionTable::new(1);
+        assert_eq!(table.hashfull(), 0);
+        for key in 0..table.capacity() as u64 {
+            table.store(&ZobristKey(key), BoardMove::NONE, 0, 1, Bound::Exact, 0);
+        }
+        assert!(table.hashfull() > 900);
+        table.new_search();
+        assert_eq!(table.hashfull(), 0);
+        table.clear();
+        assert_eq!(table.hashfull(), 0);
+    }
+
+    #[test]
+    fn concurrent_access() {
+        let table = TranspositionTable::new(1);
+        let buckets = (table.mask + 1) as u64;
+        // Every thread fights over the same few buckets, a probe must never mix two entries
+        thread::scope(|scope| {
+            for thread_index in 0..4u64 {
+                let table = &table;
+                scope.spawn(move || {
+                    for index in 0..20000u64 {
+                        let key = ZobristKey((index % 64) * buckets + index % 8 + 1);
+                        let depth = (key.to_u64() % 200) as u8;
+                        if (index + thread_index) % 2 == 0 {
+                            table.store(&key, BoardMove(key.to_u64() as u16), depth as i32, depth, Bound::Exact, 0);
+                        } else if let Some(entry) = table.probe(&key, 0) {
+                            assert_eq!(entry.board_move(), BoardMove(key.to_u64() as u16));
+                            assert_eq!(entry.score(), depth as i32);
+                            assert_eq!(entry.depth(), depth);
+                        }
+                    }
+                });
+            }
+        });
+        assert!(table.hashfull() > 0);
+    }
+}
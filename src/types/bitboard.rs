@@ -34,6 +34,8 @@ impl Bitboard {
     pub const RANK_7: Bitboard = Bitboard(0xFF000000000000u64);
     pub const RANK_8: Bitboard = Bitboard(0xFF00000000000000u64);
 
+    pub const DARK_SQUARES: Bitboard = Bitboard(0xAA55AA55AA55AA55u64);
+
     pub const A1: Bitboard = Bitboard(1u64 << 0);
     pub const B1: Bitboard = Bitboard(1u64 << 1);
     pub const C1: Bitboard = Bitboard(1u64 << 2);
@@ -136,6 +138,11 @@ impl Bitboard {
         self.0 != 0
     }
 
+    #[inline]
+    pub fn count(&self) -> u32 {
+        self.0.count_ones()
+    }
+
     #[inline]
     pub fn one_element(&self) -> bool {
         (self.0 & (self.0 - 1)) == 0
@@ -213,6 +220,14 @@ mod test {
         assert_eq!(Bitboard::from_square(&Square::H8), Bitboard::H8);
     }
 
+    #[test]
+    fn count() {
+        assert_eq!(Bitboard::EMPTY.count(), 0);
+        assert_eq!(Bitboard::A1.count(), 1);
+        assert_eq!(Bitboard::RANK_2.count(), 8);
+        assert_eq!(Bitboard::ALL.count(), 64);
+    }
+
     #[test]
     fn is_set() {
         assert_eq!(Bitboard::A1.is_set(&Square::A1), true);
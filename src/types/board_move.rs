@@ -1,3 +1,4 @@
+use crate::types::file::File;
 use crate::types::move_type::MoveType;
 use crate::types::piece_type::PieceType;
 use crate::types::square::Square;
@@ -49,11 +50,31 @@ impl BoardMove {
         MoveType((self.0 >> BoardMove::MOVE_TYPE_SHIFT) as u8)
     }
 
+    // Castling is stored as the king taking its own rook, the king always ends on the g or c file
+    #[inline]
+    pub fn castling_king_to(&self) -> Square {
+        let file = if self.square_to().0 > self.square_from().0 {
+            File::FILE_G
+        } else {
+            File::FILE_C
+        };
+        Square::from_file_rank(&file, &self.square_from().to_rank())
+    }
+
     #[inline]
     pub fn to_string(&self) -> String {
+        self.to_uci(false)
+    }
+
+    // Chess960 castling is sent as the king taking its rook, otherwise as the king destination
+    pub fn to_uci(&self, chess960: bool) -> String {
         let mut result = String::with_capacity(5);
         result.push_str(&self.square_from().to_string());
-        result.push_str(&self.square_to().to_string());
+        if self.move_type().is_castling() && !chess960 {
+            result.push_str(&self.castling_king_to().to_string());
+        } else {
+            result.push_str(&self.square_to().to_string());
+        }
         let promoted_piece = self.move_type().promoted_piece_type();
         if promoted_piece != PieceType::NONE {
             result.push(promoted_piece.to_char());
@@ -73,4 +94,15 @@ mod testing {
         assert_eq!(BoardMove::build_normal(&Square::C3, &Square::D4).to_string(), "c3d4");
         assert_eq!(BoardMove::build_move(&Square::H7, &Square::H8, &MoveType::PROMOTION_QUEEN).to_string(), "h7h8q");
     }
+
+    #[test]
+    fn castling_to_uci() {
+        let castling = BoardMove::build_castling(&Square::E1, &Square::H1);
+        assert_eq!(castling.to_string(), "e1g1");
+        assert_eq!(castling.to_uci(true), "e1h1");
+        let castling = BoardMove::build_castling(&Square::B8, &Square::A8);
+        assert_eq!(castling.castling_king_to(), Square::C8);
+        assert_eq!(castling.to_uci(false), "b8c8");
+        assert_eq!(castling.to_uci(true), "b8a8");
+    }
 }
@@ -83,6 +83,19 @@ impl CastlingIndex {
         CastlingIndex::unsafe_creation(castling_side.to_u32() + 2 * color.to_u32())
     }
 
+    #[inline]
+    pub fn color(&self) -> Color {
+        Color::unsafe_creation((self.to_u8() / 2) as i8)
+    }
+
+    #[inline]
+    pub fn side(&self) -> CastlingSide {
+        match self {
+            CastlingIndex::WhiteH | CastlingIndex::BlackH => CastlingSide::HSide,
+            CastlingIndex::WhiteA | CastlingIndex::BlackA => CastlingSide::ASide,
+        }
+    }
+
     #[inline]
     pub fn square_king_to(&self) -> Square {
         CastlingIndex::SQUARE_KING_TO[self.to_usize()]
@@ -191,27 +204,35 @@ mod testing {
 
     #[test]
     fn castling_index_unsafe_creation() {
-        assert_eq!(CastlingIndex::unsafe_creation(0), CastlingIndex::WhiteA);
-        assert_eq!(CastlingIndex::unsafe_creation(1), CastlingIndex::WhiteH);
-        assert_eq!(CastlingIndex::unsafe_creation(2), CastlingIndex::BlackA);
-        assert_eq!(CastlingIndex::unsafe_creation(3), CastlingIndex::BlackH);
+        assert_eq!(CastlingIndex::unsafe_creation(0), CastlingIndex::WhiteH);
+        assert_eq!(CastlingIndex::unsafe_creation(1), CastlingIndex::WhiteA);
+        assert_eq!(CastlingIndex::unsafe_creation(2), CastlingIndex::BlackH);
+        assert_eq!(CastlingIndex::unsafe_creation(3), CastlingIndex::BlackA);
     }
 
     #[test]
     fn castling_index_from_char() {
         assert_eq!(CastlingIndex::from_char('-'), None);
-        assert_eq!(CastlingIndex::from_char('K').unwrap(), CastlingIndex::WhiteA);
-        assert_eq!(CastlingIndex::from_char('Q').unwrap(), CastlingIndex::WhiteH);
-        assert_eq!(CastlingIndex::from_char('k').unwrap(), CastlingIndex::BlackA);
-        assert_eq!(CastlingIndex::from_char('q').unwrap(), CastlingIndex::BlackH);
+        assert_eq!(CastlingIndex::from_char('K').unwrap(), CastlingIndex::WhiteH);
+        assert_eq!(CastlingIndex::from_char('Q').unwrap(), CastlingIndex::WhiteA);
+        assert_eq!(CastlingIndex::from_char('k').unwrap(), CastlingIndex::BlackH);
+        assert_eq!(CastlingIndex::from_char('q').unwrap(), CastlingIndex::BlackA);
     }
 
     #[test]
     fn castling_index_to_char() {
-        assert_eq!(CastlingIndex::WhiteA.to_char(), 'K');
-        assert_eq!(CastlingIndex::WhiteH.to_char(), 'Q');
-        assert_eq!(CastlingIndex::BlackA.to_char(), 'k');
-        assert_eq!(CastlingIndex::BlackH.to_char(), 'q');
+        assert_eq!(CastlingIndex::WhiteH.to_char(), 'K');
+        assert_eq!(CastlingIndex::WhiteA.to_char(), 'Q');
+        assert_eq!(CastlingIndex::BlackH.to_char(), 'k');
+        assert_eq!(CastlingIndex::BlackA.to_char(), 'q');
+    }
+
+    #[test]
+    fn castling_index_side() {
+        assert_eq!(CastlingIndex::WhiteH.side(), CastlingSide::HSide);
+        assert_eq!(CastlingIndex::WhiteA.side(), CastlingSide::ASide);
+        assert_eq!(CastlingIndex::BlackH.side(), CastlingSide::HSide);
+        assert_eq!(CastlingIndex::BlackA.side(), CastlingSide::ASide);
     }
 
     #[test]
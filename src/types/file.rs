@@ -1,5 +1,3 @@
-use std::convert::From;
-
 #[derive(PartialOrd, PartialEq, Eq, Copy, Clone, Debug)]
 pub struct File(pub i8);
 
@@ -39,10 +37,7 @@ impl File {
 
     #[inline]
     pub fn from_char(input: char) -> Option<File> {
-        if input >= 'a' && input <= 'h' {
-            Some(File(u8::from(input as u8 - b'a') as i8));
-        }
-        None
+        if ('a'..='h').contains(&input) { Some(File((input as u8 - b'a') as i8)) } else { None }
     }
 
     #[inline]
@@ -72,6 +67,14 @@ mod test {
         assert_eq!(File::FILE_H.to_char(), 'h');
     }
 
+    #[test]
+    fn from_char() {
+        assert_eq!(File::from_char('a'), Some(File::FILE_A));
+        assert_eq!(File::from_char('h'), Some(File::FILE_H));
+        assert_eq!(File::from_char('i'), None);
+        assert_eq!(File::from_char('1'), None);
+    }
+
     #[test]
     fn reverse() {
         assert_eq!(File::FILE_A.reverse(), File::FILE_H);
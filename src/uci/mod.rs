@@ -0,0 +1,2 @@
+pub mod uci_command;
+pub mod uci_engine;
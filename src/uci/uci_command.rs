@@ -0,0 +1,172 @@
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct GoParams {
+    pub wtime: Option<u64>,
+    pub btime: Option<u64>,
+    pub winc: Option<u64>,
+    pub binc: Option<u64>,
+    pub moves_to_go: Option<u64>,
+    pub move_time: Option<u64>,
+    pub depth: Option<u8>,
+    pub nodes: Option<u64>,
+    pub mate: Option<u8>,
+    pub infinite: bool,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum UciCommand {
+    Uci,
+    IsReady,
+    UciNewGame,
+    Position { fen: Option<String>, moves: Vec<String> },
+    Go(GoParams),
+    Stop,
+    Quit,
+    SetOption { name: String, value: Option<String> },
+    Unknown(String),
+}
+
+impl UciCommand {
+    const START_POSITION: &'static str = "startpos";
+
+    pub fn parse(line: &str) -> UciCommand {
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("uci") => UciCommand::Uci,
+            Some("isready") => UciCommand::IsReady,
+            Some("ucinewgame") => UciCommand::UciNewGame,
+            Some("position") => UciCommand::parse_position(tokens.collect()),
+            Some("go") => UciCommand::Go(UciCommand::parse_go(tokens.collect())),
+            Some("stop") => UciCommand::Stop,
+            Some("quit") => UciCommand::Quit,
+            Some("setoption") => UciCommand::parse_set_option(tokens.collect()),
+            _ => UciCommand::Unknown(line.trim().to_string()),
+        }
+    }
+
+    fn parse_position(tokens: Vec<&str>) -> UciCommand {
+        let moves_index = tokens.iter().position(|&token| token == "moves").unwrap_or(tokens.len());
+        let fen = match tokens.first() {
+            Some(&UciCommand::START_POSITION) => None,
+            Some(&"fen") => Some(tokens[1..moves_index].join(" ")),
+            _ => return UciCommand::Unknown(format!("position {}", tokens.join(" "))),
+        };
+        let moves = tokens.iter()
+            .skip(moves_index + 1)
+            .map(|token| token.to_string())
+            .collect();
+        UciCommand::Position { fen, moves }
+    }
+
+    fn parse_go(tokens: Vec<&str>) -> GoParams {
+        let mut params = GoParams::default();
+        let mut index = 0;
+        while index < tokens.len() {
+            let value = tokens.get(index + 1);
+            match tokens[index] {
+                "wtime" => params.wtime = parse_value(value),
+                "btime" => params.btime = parse_value(value),
+                "winc" => params.winc = parse_value(value),
+                "binc" => params.binc = parse_value(value),
+                "movestogo" => params.moves_to_go = parse_value(value),
+                "movetime" => params.move_time = parse_value(value),
+                "depth" => params.depth = parse_value(value),
+                "nodes" => params.nodes = parse_value(value),
+                "mate" => params.mate = parse_value(value),
+                "infinite" => {
+                    params.infinite = true;
+                    index += 1;
+                    continue;
+                }
+                _ => {
+                    index += 1;
+                    continue;
+                }
+            }
+            index += 2;
+        }
+        params
+    }
+
+    fn parse_set_option(tokens: Vec<&str>) -> UciCommand {
+        let name_index = tokens.iter().position(|&token| token == "name");
+        let value_index = tokens.iter().position(|&token| token == "value");
+        let name_index = match name_index {
+            Some(index) if value_index.is_none_or(|value| value > index) => index,
+            _ => return UciCommand::Unknown(format!("setoption {}", tokens.join(" "))),
+        };
+        let name_end = value_index.unwrap_or(tokens.len());
+        let name = tokens[name_index + 1..name_end].join(" ");
+        let value = value_index.map(|index| tokens[index + 1..].join(" "));
+        UciCommand::SetOption { name, value }
+    }
+}
+
+fn parse_value<T: std::str::FromStr>(value: Option<&&str>) -> Option<T> {
+    value.and_then(|value| value.parse().ok())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn simple_commands() {
+        assert_eq!(UciCommand::parse("uci"), UciCommand::Uci);
+        assert_eq!(UciCommand::parse("isready"), UciCommand::IsReady);
+        assert_eq!(UciCommand::parse("ucinewgame"), UciCommand::UciNewGame);
+        assert_eq!(UciCommand::parse("stop"), UciCommand::Stop);
+        assert_eq!(UciCommand::parse("quit"), UciCommand::Quit);
+        assert_eq!(UciCommand::parse("  quit  "), UciCommand::Quit);
+        assert_eq!(UciCommand::parse("xyzzy"), UciCommand::Unknown("xyzzy".to_string()));
+    }
+
+    #[test]
+    fn position_startpos() {
+        assert_eq!(UciCommand::parse("position startpos"),
+                   UciCommand::Position { fen: None, moves: vec![] });
+        assert_eq!(UciCommand::parse("position startpos moves e2e4 e7e5"),
+                   UciCommand::Position { fen: None, moves: vec!["e2e4".to_string(), "e7e5".to_string()] });
+    }
+
+    #[test]
+    fn position_fen() {
+        assert_eq!(UciCommand::parse("position fen 5k2/8/8/8/8/8/8/4K3 w - - 0 1 moves e1e2"),
+                   UciCommand::Position {
+                       fen: Some("5k2/8/8/8/8/8/8/4K3 w - - 0 1".to_string()),
+                       moves: vec!["e1e2".to_string()],
+                   });
+    }
+
+    #[test]
+    fn go() {
+        assert_eq!(UciCommand::parse("go wtime 1000 btime 2000 winc 10 binc 20 movestogo 5"),
+                   UciCommand::Go(GoParams {
+                       wtime: Some(1000),
+                       btime: Some(2000),
+                       winc: Some(10),
+                       binc: Some(20),
+                       moves_to_go: Some(5),
+                       ..GoParams::default()
+                   }));
+        assert_eq!(UciCommand::parse("go infinite"),
+                   UciCommand::Go(GoParams { infinite: true, ..GoParams::default() }));
+        assert_eq!(UciCommand::parse("go depth 6 nodes 1000 movetime 300 mate 3"),
+                   UciCommand::Go(GoParams {
+                       depth: Some(6),
+                       nodes: Some(1000),
+                       move_time: Some(300),
+                       mate: Some(3),
+                       ..GoParams::default()
+                   }));
+    }
+
+    #[test]
+    fn set_option() {
+        assert_eq!(UciCommand::parse("setoption name Hash value 32"),
+                   UciCommand::SetOption { name: "Hash".to_string(), value: Some("32".to_string()) });
+        assert_eq!(UciCommand::parse("setoption name Clear Hash"),
+                   UciCommand::SetOption { name: "Clear Hash".to_string(), value: None });
+        assert_eq!(UciCommand::parse("setoption value 1 name Hash"),
+                   UciCommand::Unknown("setoption value 1 name Hash".to_string()));
+    }
+}
@@ -0,0 +1,457 @@
+use std::io;
+use std::io::{BufRead, Write};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::advanced::attack_info::AttackInfo;
+use crate::advanced::board::Board;
+use crate::advanced::move_list::MoveList;
+use crate::book::polyglot_book::PolyglotBook;
+use crate::search::score::{is_mate, mate_moves};
+use crate::search::search_params::SearchParams;
+use crate::search::searcher::{SearchResult, Searcher};
+use crate::search::time_manager::TimeManager;
+use crate::search::transposition_table::TranspositionTable;
+use crate::types::board_move::BoardMove;
+use crate::uci::uci_command::{GoParams, UciCommand};
+use crate::utils::random::Random;
+
+pub struct UciEngine<W: Write + Send + 'static> {
+    board: Board,
+    attack_info: AttackInfo,
+    move_list: MoveList,
+    searcher: Option<Searcher>,
+    search_thread: Option<JoinHandle<Searcher>>,
+    stop: Arc<AtomicBool>,
+    output: Arc<Mutex<W>>,
+    // Castling moves are sent as the king taking its rook
+    chess960: bool,
+    book: Option<PolyglotBook>,
+    own_book: bool,
+    best_book_move: bool,
+    random: Random,
+}
+
+impl<W: Write + Send + 'static> UciEngine<W> {
+    pub const NAME: &'static str = "Urubu";
+    pub const AUTHOR: &'static str = "Ratosh";
+
+    pub fn new(output: W) -> Self {
+        let searcher = Searcher::new();
+        Self {
+            board: Board::default(),
+            attack_info: AttackInfo::new(),
+            move_list: MoveList::new(),
+            stop: searcher.stop_flag(),
+            searcher: Some(searcher),
+            search_thread: None,
+            output: Arc::new(Mutex::new(output)),
+            chess960: false,
+            book: None,
+            own_book: false,
+            best_book_move: false,
+            random: Random::with_seed(SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_nanos() as u64)),
+        }
+    }
+
+    pub fn run<R: BufRead>(&mut self, input: R) -> io::Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if !self.execute(UciCommand::parse(&line))? {
+                return Ok(());
+            }
+        }
+        // Input closed, let a running search report its move
+        self.wait_search();
+        Ok(())
+    }
+
+    // Returns false when the engine should quit
+    pub fn execute(&mut self, command: UciCommand) -> io::Result<bool> {
+        match command {
+            UciCommand::Uci => {
+                self.send(&format!("id name {}", UciEngine::<W>::NAME))?;
+                self.send(&format!("id author {}", UciEngine::<W>::AUTHOR))?;
+                self.send(&format!("option name Hash type spin default {} min {} max {}",
+                                   TranspositionTable::DEFAULT_SIZE_MB,
+                                   TranspositionTable::MIN_SIZE_MB,
+                                   TranspositionTable::MAX_SIZE_MB))?;
+                self.send(&format!("option name Threads type spin default 1 min 1 max {}", Searcher::MAX_THREADS))?;
+                self.send("option name Clear Hash type button")?;
+                self.send("option name UCI_Chess960 type check default false")?;
+                self.send("option name OwnBook type check default false")?;
+                self.send("option name BookFile type string default <empty>")?;
+                self.send("option name BestBookMove type check default false")?;
+                for option in SearchParams::new().uci_options() {
+                    self.send(&option)?;
+                }
+                self.send("uciok")?;
+            }
+            UciCommand::IsReady => self.send("readyok")?,
+            UciCommand::UciNewGame => {
+                self.stop_search();
+                self.board = Board::default();
+                self.searcher_mut().clear_transposition_table();
+                self.searcher_mut().clear_history();
+            }
+            UciCommand::Position { fen, moves } => {
+                self.stop_search();
+                self.set_position(fen, &moves)?;
+            }
+            UciCommand::Go(params) => self.go(&params),
+            UciCommand::Stop => self.stop_search(),
+            UciCommand::Quit => {
+                self.stop_search();
+                return Ok(false);
+            }
+            UciCommand::SetOption { name, value } => {
+                self.stop_search();
+                self.set_option(&name, value)?;
+            }
+            UciCommand::Unknown(line) => self.send(&format!("info string unknown command {}", line))?,
+        }
+        Ok(true)
+    }
+
+    fn send(&self, line: &str) -> io::Result<()> {
+        send_line(&self.output, line)
+    }
+
+    fn set_option(&mut self, name: &str, value: Option<String>) -> io::Result<()> {
+        match name.to_lowercase().as_str() {
+            "hash" => match value.and_then(|value| value.parse::<usize>().ok()) {
+                Some(size_mb) => self.searcher_mut().resize_transposition_table(size_mb),
+                None => self.send("info string invalid Hash value")?,
+            },
+            "threads" => match value.and_then(|value| value.parse::<usize>().ok()) {
+                Some(threads) if (1..=Searcher::MAX_THREADS).contains(&threads) => self.searcher_mut().set_threads(threads),
+                _ => self.send("info string invalid Threads value")?,
+            },
+            "clear hash" => self.searcher_mut().clear_transposition_table(),
+            "uci_chess960" => match value.map(|value| value.to_lowercase()).as_deref() {
+                Some("true") => self.chess960 = true,
+                Some("false") => self.chess960 = false,
+                _ => self.send("info string invalid UCI_Chess960 value")?,
+            },
+            "ownbook" => match value.map(|value| value.to_lowercase()).as_deref() {
+                Some("true") => self.own_book = true,
+                Some("false") => self.own_book = false,
+                _ => self.send("info string invalid OwnBook value")?,
+            },
+            "bestbookmove" => match value.map(|value| value.to_lowercase()).as_deref() {
+                Some("true") => self.best_book_move = true,
+                Some("false") => self.best_book_move = false,
+                _ => self.send("info string invalid BestBookMove value")?,
+            },
+            "bookfile" => match value.filter(|path| path != "<empty>") {
+                Some(path) => match PolyglotBook::open(&path) {
+                    Ok(book) => self.book = Some(book),
+                    Err(error) => {
+                        self.book = None;
+                        self.send(&format!("info string invalid book {}: {}", path, error))?;
+                    }
+                },
+                None => self.book = None,
+            },
+            // Search parameters, for testing changes in self play
+            _ => {
+                let mut params = *self.searcher_mut().params();
+                match params.set(name, value.as_deref().unwrap_or("")) {
+                    Ok(true) => self.searcher_mut().set_params(params),
+                    Ok(false) => self.send(&format!("info string unknown option {}", name))?,
+                    Err(message) => self.send(&format!("info string {}", message))?,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn searcher_mut(&mut self) -> &mut Searcher {
+        self.searcher.as_mut().expect("searcher is idle")
+    }
+
+    fn set_position(&mut self, fen: Option<String>, moves: &[String]) -> io::Result<()> {
+        self.board = match fen {
+            Some(fen) => match Board::try_from_fen(&fen) {
+                Ok(board) => board,
+                Err(error) => return self.send(&format!("info string invalid fen {}", error)),
+            },
+            None => Board::default(),
+        };
+        for move_str in moves {
+            match self.find_move(move_str) {
+                Some(board_move) => {
+                    self.board.do_move(&board_move);
+                }
+                None => {
+                    self.send(&format!("info string invalid move {}", move_str))?;
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn go(&mut self, params: &GoParams) {
+        self.stop_search();
+        self.stop.store(false, Ordering::Relaxed);
+
+        if let Some(book_move) = self.book_move(params) {
+            send_line(&self.output, &format!("bestmove {}", uci_move(&book_move, self.chess960))).ok();
+            return;
+        }
+
+        let infinite = params.infinite;
+        let board = self.board.clone_position();
+        let fallback_move = self.first_legal_move().unwrap_or(BoardMove::NONE);
+        let mut searcher = self.searcher.take().expect("searcher is idle");
+        searcher.set_go_params(params, board.color_to_move);
+        let stop = self.stop.clone();
+        let output = self.output.clone();
+        let chess960 = self.chess960;
+
+        self.search_thread = Some(thread::spawn(move || {
+            let mut result = searcher.iterative_deepening(&board, TimeManager::MAX_DEPTH, |result| {
+                send_line(&output, &info_line(result, chess960)).ok();
+            });
+            // UCI forbids reporting the best move of an infinite search before stop
+            while infinite && !stop.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(1));
+            }
+            if result.best_move == BoardMove::NONE {
+                result.best_move = fallback_move;
+            }
+            send_line(&output, &format!("bestmove {}", uci_move(&result.best_move, chess960))).ok();
+            searcher
+        }));
+    }
+
+    // Infinite searches are analysis, they never use the book
+    fn book_move(&mut self, params: &GoParams) -> Option<BoardMove> {
+        if !self.own_book || params.infinite {
+            return None;
+        }
+        let book = self.book.as_ref()?;
+        if self.best_book_move {
+            book.best_move(&self.board)
+        } else {
+            book.weighted_move(&self.board, &mut self.random)
+        }
+    }
+
+    fn stop_search(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.wait_search();
+    }
+
+    fn wait_search(&mut self) {
+        if let Some(search_thread) = self.search_thread.take() {
+            self.searcher = Some(search_thread.join().expect("search thread panicked"));
+        }
+    }
+
+    fn find_move(&mut self, move_str: &str) -> Option<BoardMove> {
+        let chess960 = self.chess960;
+        self.legal_moves().iter().copied().find(|board_move| board_move.to_uci(chess960) == move_str)
+    }
+
+    fn first_legal_move(&mut self) -> Option<BoardMove> {
+        self.legal_moves().first().copied()
+    }
+
+    fn legal_moves(&mut self) -> &[BoardMove] {
+        self.board.legal_moves(&mut self.move_list, &mut self.attack_info)
+    }
+}
+
+fn send_line<W: Write>(output: &Mutex<W>, line: &str) -> io::Result<()> {
+    let mut output = output.lock().expect("output lock poisoned");
+    writeln!(output, "{}", line)?;
+    output.flush()
+}
+
+fn info_line(result: &SearchResult, chess960: bool) -> String {
+    let score = if is_mate(result.score) {
+        format!("mate {}", mate_moves(result.score))
+    } else {
+        format!("cp {}", result.score)
+    };
+    let principal_variation: Vec<String> = result.principal_variation.iter()
+        .map(|board_move| board_move.to_uci(chess960))
+        .collect();
+    format!("info depth {} score {} nodes {} hashfull {} pv {}",
+            result.depth, score, result.nodes, result.hashfull, principal_variation.join(" "))
+}
+
+#[inline]
+fn uci_move(board_move: &BoardMove, chess960: bool) -> String {
+    if *board_move == BoardMove::NONE {
+        "0000".to_string()
+    } else {
+        board_move.to_uci(chess960)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::types::square::Square;
+
+    #[derive(Clone)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedBuffer {
+        fn new() -> Self {
+            SharedBuffer(Arc::new(Mutex::new(Vec::new())))
+        }
+
+        fn lines(&self) -> Vec<String> {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+                .lines()
+                .map(|line| line.to_string())
+                .collect()
+        }
+    }
+
+    fn run_transcript(transcript: &str) -> Vec<String> {
+        let buffer = SharedBuffer::new();
+        UciEngine::new(buffer.clone()).run(transcript.as_bytes()).unwrap();
+        buffer.lines()
+    }
+
+    #[test]
+    fn handshake() {
+        let output = run_transcript("uci\nisready\nquit\n");
+        assert_eq!(output[..2], ["id name Urubu", "id author Ratosh"]);
+        assert!(output.contains(&"option name Hash type spin default 16 min 1 max 65536".to_string()));
+        assert_eq!(output[output.len() - 2..], ["uciok", "readyok"]);
+    }
+
+    #[test]
+    fn set_option() {
+        let output = run_transcript("setoption name Hash value 1\nsetoption name Clear Hash\nsetoption name Foo value 1\nisready\n");
+        assert_eq!(output, vec!["info string unknown option Foo", "readyok"]);
+    }
+
+    #[test]
+    fn search_params() {
+        let mut engine = UciEngine::new(SharedBuffer::new());
+        engine.execute(UciCommand::parse("setoption name NullMove value false")).unwrap();
+        engine.execute(UciCommand::parse("setoption name FutilityMargin value 150")).unwrap();
+        assert!(!engine.searcher_mut().params().null_move);
+        assert_eq!(engine.searcher_mut().params().futility_margin, 150);
+
+        let output = run_transcript("uci\nsetoption name RazoringDepth value 99\n");
+        assert!(output.contains(&"option name LateMoveReductions type check default true".to_string()));
+        assert_eq!(output.last().unwrap(), "info string invalid RazoringDepth value 99");
+    }
+
+    #[test]
+    fn threads() {
+        let buffer = SharedBuffer::new();
+        let mut engine = UciEngine::new(buffer.clone());
+        engine.execute(UciCommand::parse("setoption name Threads value 3")).unwrap();
+        engine.execute(UciCommand::parse("setoption name Threads value 0")).unwrap();
+        assert_eq!(engine.searcher_mut().threads(), 3);
+        engine.execute(UciCommand::parse("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1")).unwrap();
+        engine.execute(UciCommand::parse("go depth 3")).unwrap();
+        engine.wait_search();
+        let output = buffer.lines();
+        assert_eq!(output[0], "info string invalid Threads value");
+        assert_eq!(output.last().unwrap(), "bestmove a1a8");
+    }
+
+    #[test]
+    fn quit_stops_reading() {
+        let output = run_transcript("quit\nisready\n");
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn position_moves() {
+        let mut engine = UciEngine::new(SharedBuffer::new());
+        engine.execute(UciCommand::parse("position startpos moves e2e4 e7e5 g1f3")).unwrap();
+        assert!(engine.board.to_fen().starts_with("rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1"));
+    }
+
+    #[test]
+    fn chess960_castling() {
+        let mut engine = UciEngine::new(SharedBuffer::new());
+        let fen = "position fen 4k3/8/8/8/8/8/8/1R3KR1 w GB - 0 1 moves f1g1";
+        engine.execute(UciCommand::parse(fen)).unwrap();
+        assert_eq!(engine.board.to_fen(), "4k3/8/8/8/8/8/8/1R3RK1 b - - 1 1");
+
+        engine.execute(UciCommand::parse("setoption name UCI_Chess960 value true")).unwrap();
+        engine.execute(UciCommand::parse("position fen 4k3/8/8/8/8/8/8/1R3KR1 w GB - 0 1 moves f1b1")).unwrap();
+        assert_eq!(engine.board.to_fen(), "4k3/8/8/8/8/8/8/2KR2R1 b - - 1 1");
+    }
+
+    #[test]
+    fn book_move() {
+        let path = std::env::temp_dir().join(format!("urubu_uci_book_{}.bin", std::process::id()));
+        let raw_move = Square::D4.to_u16() | Square::D2.to_u16() << 6;
+        let mut bytes = Board::default().polyglot_key().to_be_bytes().to_vec();
+        bytes.extend_from_slice(&raw_move.to_be_bytes());
+        bytes.extend_from_slice(&[0, 1, 0, 0, 0, 0]);
+        std::fs::write(&path, bytes).unwrap();
+
+        let transcript = format!("setoption name BookFile value {}\nsetoption name OwnBook value true\n\
+            position startpos\ngo depth 5\nposition startpos moves d2d4\ngo depth 1\n", path.display());
+        let output = run_transcript(&transcript);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(output[0], "bestmove d2d4");
+        assert!(output[1].starts_with("info depth 1"));
+        assert!(run_transcript("setoption name BookFile value /nonexistent/book.bin\n")[0].starts_with("info string invalid book"));
+    }
+
+    #[test]
+    fn invalid_move() {
+        let output = run_transcript("position startpos moves e2e5\nquit\n");
+        assert_eq!(output, vec!["info string invalid move e2e5"]);
+    }
+
+    #[test]
+    fn invalid_fen() {
+        let output = run_transcript("position fen 8/8/8/8/8/8/8/8 w - - 0 1\nquit\n");
+        assert_eq!(output, vec!["info string invalid fen missing king for w"]);
+    }
+
+    #[test]
+    fn go_depth_finds_mate() {
+        let output = run_transcript("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\ngo depth 3\n");
+        assert!(output.iter().any(|line| line.starts_with("info depth 3 score mate 1")));
+        assert_eq!(output.last().unwrap(), "bestmove a1a8");
+    }
+
+    #[test]
+    fn go_infinite_waits_for_stop() {
+        let output = run_transcript("position startpos\ngo infinite\nisready\nstop\nquit\n");
+        let ready_index = output.iter().position(|line| line == "readyok").unwrap();
+        let best_move_index = output.iter().position(|line| line.starts_with("bestmove")).unwrap();
+        assert!(ready_index < best_move_index);
+        assert_ne!(output[best_move_index], "bestmove 0000");
+    }
+
+    #[test]
+    fn go_without_moves() {
+        let output = run_transcript("position fen 7k/8/8/8/8/8/5q2/7K w - - 0 1\ngo depth 2\n");
+        assert_eq!(output.last().unwrap(), "bestmove 0000");
+    }
+}
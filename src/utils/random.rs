@@ -10,6 +10,11 @@ impl Random {
         Random(Random::INITIAL_SEED)
     }
 
+    // A zero seed would only ever produce zeros
+    pub fn with_seed(seed: u64) -> Self {
+        Random(if seed == 0 { Random::INITIAL_SEED } else { seed as i64 })
+    }
+
     pub fn next(&mut self) -> u64 {
         self.0 ^= self.0 >> 12;
         self.0 ^= self.0 << 25;